@@ -1,33 +1,265 @@
-use crate::model::{Collection, Deck};
-use ratatui::widgets::ListState;
-#[derive(PartialEq)]
+use crate::formats::{self, CsvOptions, ExportFormat};
+use crate::model::{
+    AnswerCheck, AnswerMode, CardDirection, Collection, CollectionChange, CollectionError,
+    DECK_SEPARATOR, Deck, DeckLimits, DeckSource, DeckTreeNode, DeckUpdate, FlashCard,
+    LearningSteps, QuizQuestion, ReviewLogEntry, Shuffler, SourceFormat, TagExpr, check_answer,
+};
+use crate::storage::Storage;
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use ratatui::widgets::{ListState, TableState};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use uuid::Uuid;
+
+pub const DEFAULT_REQUEUE_GAP: usize = 3;
+const HISTORY_LIMIT: usize = 100;
+#[derive(PartialEq, Clone, Copy)]
 pub enum CurrentScreen {
     Main,
     Studying,
     AddingDeck,
+    Browsing,
+    EditingCard,
+    DeckPrompt,
+    ConfirmingDeckDeletion,
+    Statistics,
+    UpdatePreview,
+    SourceChanged,
     Exiting,
 }
 pub enum CurrentlyEditing {
     Name,
     Path,
+    Options,
 }
+#[derive(Clone)]
 pub struct StudyState {
-    pub deck_index: usize,
     pub card_index: usize,
     pub is_answer_visible: bool,
-    pub indexes: Vec<usize>,
+    pub indexes: Vec<(usize, usize)>,
+    pub tag_filter: Option<String>,
+    pub shown_at: Instant,
+    pub typed_answer: String,
+    pub answer_check: Option<AnswerCheck>,
+    pub quiz: Option<QuizQuestion>,
+    pub chosen: Option<usize>,
+    pub practice: bool,
+    pub quiz_score: (usize, usize),
+    pub failed: HashSet<(usize, usize)>,
+    pub session: Uuid,
+}
+impl StudyState {
+    pub fn remaining(&self) -> usize {
+        self.indexes.len() - self.card_index
+    }
+    pub fn requeued_remaining(&self) -> usize {
+        (self.card_index..self.indexes.len())
+            .filter(|&i| self.indexes[..i].contains(&self.indexes[i]))
+            .count()
+    }
+}
+pub struct HistoryEntry {
+    pub label: &'static str,
+    change: CollectionChange,
+    study_before: Option<StudyState>,
+    study_after: Option<StudyState>,
+}
+pub struct TagFilter {
+    pub text: String,
+    pub expr: TagExpr,
+}
+#[derive(Clone, Copy, PartialEq)]
+pub enum BrowserColumn {
+    Question,
+    Answer,
+    Due,
+    Interval,
+    Ease,
+    Repetitions,
+}
+impl BrowserColumn {
+    pub const ALL: [BrowserColumn; 6] = [
+        BrowserColumn::Question,
+        BrowserColumn::Answer,
+        BrowserColumn::Due,
+        BrowserColumn::Interval,
+        BrowserColumn::Ease,
+        BrowserColumn::Repetitions,
+    ];
+    pub fn title(self) -> &'static str {
+        match self {
+            BrowserColumn::Question => "Pytanie",
+            BrowserColumn::Answer => "Odpowiedź",
+            BrowserColumn::Due => "Powtórka",
+            BrowserColumn::Interval => "Interwał",
+            BrowserColumn::Ease => "Łatwość",
+            BrowserColumn::Repetitions => "Powtórzenia",
+        }
+    }
+    fn position(self) -> usize {
+        BrowserColumn::ALL
+            .iter()
+            .position(|c| *c == self)
+            .unwrap_or(0)
+    }
+    pub fn next(self) -> BrowserColumn {
+        BrowserColumn::ALL[(self.position() + 1) % BrowserColumn::ALL.len()]
+    }
+    pub fn previous(self) -> BrowserColumn {
+        let len = BrowserColumn::ALL.len();
+        BrowserColumn::ALL[(self.position() + len - 1) % len]
+    }
+    fn compare(self, a: &FlashCard, b: &FlashCard) -> Ordering {
+        match self {
+            BrowserColumn::Question => a
+                .get_question()
+                .to_lowercase()
+                .cmp(&b.get_question().to_lowercase()),
+            BrowserColumn::Answer => a
+                .get_answer()
+                .to_lowercase()
+                .cmp(&b.get_answer().to_lowercase()),
+            BrowserColumn::Due => a.get_date().cmp(&b.get_date()),
+            BrowserColumn::Interval => a.get_interval().cmp(&b.get_interval()),
+            BrowserColumn::Ease => a.get_ef().total_cmp(&b.get_ef()),
+            BrowserColumn::Repetitions => a.get_repetitions().cmp(&b.get_repetitions()),
+        }
+    }
+}
+#[derive(Clone, Copy, PartialEq)]
+pub enum CardFilter {
+    All,
+    Due,
+    New,
+    Overdue,
+}
+impl CardFilter {
+    pub fn next(self) -> CardFilter {
+        match self {
+            CardFilter::All => CardFilter::Due,
+            CardFilter::Due => CardFilter::New,
+            CardFilter::New => CardFilter::Overdue,
+            CardFilter::Overdue => CardFilter::All,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            CardFilter::All => "wszystkie",
+            CardFilter::Due => "do powtórki",
+            CardFilter::New => "nowe",
+            CardFilter::Overdue => "zaległe",
+        }
+    }
+    fn matches(self, card: &FlashCard, now: NaiveDateTime) -> bool {
+        match self {
+            CardFilter::All => true,
+            CardFilter::Due => card.is_due(now),
+            CardFilter::New => card.is_new(),
+            CardFilter::Overdue => !card.is_new() && card.get_date() < now.date(),
+        }
+    }
+}
+#[derive(PartialEq)]
+pub enum EditorField {
+    Question,
+    Answer,
+    Tags,
+}
+pub struct EditorState {
+    pub deck_index: usize,
+    pub card_index: Option<usize>,
+    pub question: String,
+    pub answer: String,
+    pub tags: String,
+    pub direction: CardDirection,
+    pub focus: EditorField,
+    pub error: Option<String>,
+    pub return_screen: CurrentScreen,
+}
+pub enum DeckPromptKind {
+    Rename(usize),
+    Export {
+        deck_index: Option<usize>,
+        format: ExportFormat,
+    },
+    UpdateFromSource(usize),
+    Split {
+        deck_index: usize,
+        card_ids: Vec<Uuid>,
+    },
+    TagFilter,
+    Limits(usize),
+    LearningSteps(usize),
+}
+pub struct DeckPrompt {
+    pub kind: DeckPromptKind,
+    pub input: String,
+    pub error: Option<String>,
+}
+pub struct UpdatePreview {
+    pub deck_index: usize,
+    pub source: String,
+    pub update: DeckUpdate,
+    pub options: String,
+    pub retire_removed: bool,
+    pub scroll: u16,
+}
+pub struct BrowserState {
+    pub deck_index: Option<usize>,
+    pub query: String,
+    pub is_searching: bool,
+    pub sort_column: BrowserColumn,
+    pub sort_ascending: bool,
+    pub filter: CardFilter,
+    pub table_state: TableState,
+    pub confirm_delete: bool,
+    pub marked: HashSet<Uuid>,
+}
+#[derive(PartialEq)]
+pub enum NotificationKind {
+    Info,
+    Error,
+}
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub lines: Vec<String>,
 }
 pub struct App {
     pub name_input: String,
     pub path_input: String,
+    pub options_input: String,
     pub current_screen: CurrentScreen,
     pub currently_editing: Option<CurrentlyEditing>,
     pub deck_list_state: ListState,
+    pub collapsed_decks: HashSet<String>,
+    pub tag_list_state: ListState,
+    pub tags_focused: bool,
+    pub tag_filter: Option<TagFilter>,
     pub collection: Collection,
+    pub storage: Box<dyn Storage>,
     pub study_state: Option<StudyState>,
+    pub browser_state: Option<BrowserState>,
+    pub editor_state: Option<EditorState>,
+    pub deck_prompt: Option<DeckPrompt>,
+    pub update_preview: Option<UpdatePreview>,
+    pub watch_sources: bool,
+    pub requeue_gap: usize,
+    pub undo_stack: Vec<HistoryEntry>,
+    pub redo_stack: Vec<HistoryEntry>,
+    pub source_change: Option<usize>,
+    pub merge_source: Option<Uuid>,
+    pub last_session: Option<Uuid>,
+    pub statistics_deck: Option<usize>,
+    pub message: Option<String>,
+    pub notifications: Vec<Notification>,
 }
 impl App {
-    pub fn new(collection: Collection) -> App {
+    pub fn new(collection: Collection, storage: Box<dyn Storage>) -> App {
         let mut deck_list_state = ListState::default();
         if !collection.decks.is_empty() {
             deck_list_state.select(Some(0));
@@ -35,14 +267,93 @@ impl App {
         App {
             name_input: String::new(),
             path_input: String::new(),
+            options_input: String::new(),
             current_screen: CurrentScreen::Main,
             currently_editing: None,
             deck_list_state,
+            collapsed_decks: HashSet::new(),
+            tag_list_state: ListState::default(),
+            tags_focused: false,
+            tag_filter: None,
             collection,
+            storage,
             study_state: None,
+            browser_state: None,
+            editor_state: None,
+            deck_prompt: None,
+            update_preview: None,
+            watch_sources: false,
+            requeue_gap: DEFAULT_REQUEUE_GAP,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            source_change: None,
+            merge_source: None,
+            last_session: None,
+            statistics_deck: None,
+            message: None,
+            notifications: vec![],
         }
     }
 
+    pub fn deck_rows(&self) -> Vec<DeckTreeNode> {
+        self.collection
+            .deck_tree()
+            .into_iter()
+            .filter(|node| {
+                !self.collapsed_decks.iter().any(|collapsed| {
+                    node.path
+                        .strip_prefix(collapsed.as_str())
+                        .is_some_and(|rest| rest.starts_with(DECK_SEPARATOR))
+                })
+            })
+            .collect()
+    }
+    pub fn selected_deck_row(&self) -> Option<DeckTreeNode> {
+        let selected = self.deck_list_state.selected()?;
+        self.deck_rows().into_iter().nth(selected)
+    }
+    pub fn selected_deck_index(&self) -> Option<usize> {
+        self.selected_deck_row()?.deck_index
+    }
+    fn select_deck(&mut self, deck_index: usize) {
+        let tree = self.collection.deck_tree();
+        if let Some(node) = tree.iter().find(|node| node.deck_index == Some(deck_index)) {
+            self.collapsed_decks.retain(|collapsed| {
+                !node
+                    .path
+                    .strip_prefix(collapsed.as_str())
+                    .is_some_and(|rest| rest.starts_with(DECK_SEPARATOR))
+            });
+        }
+        let row = self
+            .deck_rows()
+            .iter()
+            .position(|node| node.deck_index == Some(deck_index));
+        self.deck_list_state.select(row);
+    }
+    fn select_deck_path(&mut self, path: &str) {
+        if let Some(row) = self.deck_rows().iter().position(|node| node.path == path) {
+            self.deck_list_state.select(Some(row));
+        }
+    }
+    pub fn expand_selected_deck(&mut self) {
+        if let Some(row) = self.selected_deck_row()
+            && row.has_children
+        {
+            self.collapsed_decks.remove(&row.path);
+        }
+    }
+    pub fn collapse_selected_deck(&mut self) {
+        let Some(row) = self.selected_deck_row() else {
+            return;
+        };
+        if row.has_children && !self.collapsed_decks.contains(&row.path) {
+            self.collapsed_decks.insert(row.path);
+        } else if let Some((parent, _)) = row.path.rsplit_once(DECK_SEPARATOR) {
+            let parent = parent.to_string();
+            self.select_deck_path(&parent);
+        }
+    }
     pub fn select_next(&mut self) {
         self.deck_list_state.select_next();
     }
@@ -53,65 +364,1378 @@ impl App {
         if let Some(edit_mode) = &self.currently_editing {
             match edit_mode {
                 CurrentlyEditing::Name => self.currently_editing = Some(CurrentlyEditing::Path),
-                CurrentlyEditing::Path => self.currently_editing = Some(CurrentlyEditing::Name),
+                CurrentlyEditing::Path => self.currently_editing = Some(CurrentlyEditing::Options),
+                CurrentlyEditing::Options => self.currently_editing = Some(CurrentlyEditing::Name),
             };
         } else {
             self.currently_editing = Some(CurrentlyEditing::Name);
         }
     }
     pub fn toggle_study(&mut self) {
-        if let Some(idx) = self.deck_list_state.selected() {
-            if let Some(deck) = self.collection.decks.get(idx) {
-                let today = chrono::Local::now().naive_local().date();
-                let indices = deck.get_cards_to_review_indices(today);
-                if indices.is_empty() {
-                    return;
+        if let Some(row) = self.selected_deck_row() {
+            self.start_study(row.decks);
+        }
+    }
+    pub fn study_all_decks(&mut self) {
+        self.start_study((0..self.collection.decks.len()).collect());
+    }
+    fn start_study(&mut self, decks: Vec<usize>) {
+        let now = chrono::Local::now().naive_local();
+        let filter = self.tag_filter.as_ref().map(|filter| &filter.expr);
+        let indices: Vec<(usize, usize)> = decks
+            .into_iter()
+            .filter_map(|idx| Some((idx, self.collection.decks.get(idx)?)))
+            .flat_map(|(idx, deck)| {
+                deck.get_cards_to_review_indices(now, filter)
+                    .into_iter()
+                    .map(move |card_idx| (idx, card_idx))
+            })
+            .collect();
+        if indices.is_empty() {
+            self.message = Some(match &self.tag_filter {
+                Some(filter) => format!("Brak kart do powtórki z tagami: {}.", filter.text),
+                None => "Brak kart do powtórki.".to_string(),
+            });
+            return;
+        }
+        let session = Uuid::new_v4();
+        self.last_session = Some(session);
+        self.study_state = Some(StudyState {
+            session,
+            card_index: 0,
+            is_answer_visible: false,
+            indexes: indices,
+            tag_filter: self.tag_filter.as_ref().map(|filter| filter.text.clone()),
+            shown_at: Instant::now(),
+            typed_answer: String::new(),
+            answer_check: None,
+            quiz: None,
+            chosen: None,
+            practice: false,
+            quiz_score: (0, 0),
+            failed: HashSet::new(),
+        });
+        self.current_screen = CurrentScreen::Studying;
+        self.prepare_quiz();
+    }
+    pub fn start_quiz(&mut self) {
+        let Some(row) = self.selected_deck_row() else {
+            return;
+        };
+        let filter = self.tag_filter.as_ref().map(|filter| &filter.expr);
+        let mut indices: Vec<(usize, usize)> = row
+            .decks
+            .into_iter()
+            .filter_map(|idx| Some((idx, self.collection.decks.get(idx)?)))
+            .flat_map(|(idx, deck)| {
+                deck.cards
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, card)| filter.is_none_or(|f| f.matches(card)))
+                    .map(move |(card_idx, _)| (idx, card_idx))
+            })
+            .collect();
+        if indices.is_empty() {
+            self.message = Some("Brak kart do quizu.".to_string());
+            return;
+        }
+        Shuffler::default().shuffle(&mut indices);
+        let session = Uuid::new_v4();
+        self.last_session = Some(session);
+        self.study_state = Some(StudyState {
+            session,
+            card_index: 0,
+            is_answer_visible: false,
+            indexes: indices,
+            tag_filter: self.tag_filter.as_ref().map(|filter| filter.text.clone()),
+            shown_at: Instant::now(),
+            typed_answer: String::new(),
+            answer_check: None,
+            quiz: None,
+            chosen: None,
+            practice: true,
+            quiz_score: (0, 0),
+            failed: HashSet::new(),
+        });
+        self.current_screen = CurrentScreen::Studying;
+        self.prepare_quiz();
+    }
+    pub fn toggle_tags_focus(&mut self) {
+        self.tags_focused = !self.tags_focused;
+        if self.tags_focused && self.tag_list_state.selected().is_none() {
+            self.tag_list_state.select(Some(0));
+        }
+    }
+    pub fn select_tag(&mut self) {
+        let now = chrono::Local::now().naive_local();
+        let tags = self.collection.tag_counts(now);
+        if let Some(count) = self.tag_list_state.selected().and_then(|idx| tags.get(idx)) {
+            self.tag_filter = Some(TagFilter {
+                text: count.tag.clone(),
+                expr: TagExpr::Tag(count.tag.clone()),
+            });
+        }
+        self.tags_focused = false;
+    }
+    pub fn start_tag_filter(&mut self) {
+        self.deck_prompt = Some(DeckPrompt {
+            kind: DeckPromptKind::TagFilter,
+            input: self
+                .tag_filter
+                .as_ref()
+                .map(|filter| filter.text.clone())
+                .unwrap_or_default(),
+            error: None,
+        });
+        self.current_screen = CurrentScreen::DeckPrompt;
+    }
+    pub fn clear_tag_filter(&mut self) {
+        self.tag_filter = None;
+    }
+    pub fn toggle_scheduler(&mut self) -> Result<()> {
+        if let Some(idx) = self.selected_deck_index() {
+            let change = CollectionChange::begin(&self.collection, &[idx]);
+            let deck = &mut self.collection.decks[idx];
+            deck.set_scheduler(deck.get_scheduler().next());
+            self.storage.save_deck(&self.collection, idx)?;
+            self.push_change("zmiana algorytmu", change, None);
+        }
+        Ok(())
+    }
+
+    fn current_study_card(&self) -> Option<(&Deck, &FlashCard)> {
+        let state = self.study_state.as_ref()?;
+        let (deck_idx, card_idx) = *state.indexes.get(state.card_index)?;
+        let deck = self.collection.decks.get(deck_idx)?;
+        Some((deck, deck.get_card(card_idx)?))
+    }
+    pub fn is_typing_answer(&self) -> bool {
+        self.study_state
+            .as_ref()
+            .is_some_and(|state| !state.is_answer_visible && !state.practice)
+            && self
+                .current_study_card()
+                .is_some_and(|(deck, _)| deck.get_answer_mode().is_typed())
+    }
+    pub fn edit_typed_answer(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(state) = &mut self.study_state {
+            edit(&mut state.typed_answer);
+        }
+    }
+    pub fn check_typed_answer(&mut self) {
+        let Some((deck, card)) = self.current_study_card() else {
+            return;
+        };
+        let mode = deck.get_answer_mode();
+        let expected = card.get_expected_answer();
+        if let Some(state) = &mut self.study_state {
+            state.answer_check = Some(check_answer(&state.typed_answer, &expected, mode));
+            state.is_answer_visible = true;
+        }
+    }
+    fn prepare_quiz(&mut self) {
+        let Some(state) = &self.study_state else {
+            return;
+        };
+        let quiz = state
+            .indexes
+            .get(state.card_index)
+            .and_then(|&(deck_idx, card_idx)| {
+                let deck = self.collection.decks.get(deck_idx)?;
+                if !state.practice && deck.get_answer_mode() != AnswerMode::Choice {
+                    return None;
+                }
+                deck.quiz_question(card_idx, &mut Shuffler::default())
+            });
+        if let Some(state) = &mut self.study_state {
+            state.quiz = quiz;
+            state.chosen = None;
+        }
+    }
+    pub fn is_choosing_answer(&self) -> bool {
+        self.study_state
+            .as_ref()
+            .is_some_and(|state| state.quiz.is_some() && !state.is_answer_visible)
+    }
+    pub fn is_quiz_practice(&self) -> bool {
+        self.study_state
+            .as_ref()
+            .is_some_and(|state| state.practice)
+    }
+    pub fn choose_answer(&mut self, choice: usize) -> Result<()> {
+        let now = chrono::Local::now().naive_local();
+        let Some(state) = &mut self.study_state else {
+            return Ok(());
+        };
+        let Some(quiz) = &state.quiz else {
+            return Ok(());
+        };
+        if state.chosen.is_some() || choice >= quiz.choices.len() {
+            return Ok(());
+        }
+        let correct = quiz.is_correct(choice);
+        state.chosen = Some(choice);
+        state.is_answer_visible = true;
+        if !state.practice {
+            return Ok(());
+        }
+        state.quiz_score.1 += 1;
+        if correct {
+            state.quiz_score.0 += 1;
+        }
+        let (deck_idx, card_idx) = state.indexes[state.card_index];
+        if let Some(card_id) = self.collection.decks[deck_idx]
+            .get_card(card_idx)
+            .map(|card| card.get_id())
+        {
+            let entry = self
+                .collection
+                .record_quiz_answer(card_id, correct, now)
+                .clone();
+            self.storage.save_quiz_answer(&self.collection, &entry)?;
+        }
+        Ok(())
+    }
+    pub fn next_quiz_card(&mut self) {
+        let Some(state) = &mut self.study_state else {
+            return;
+        };
+        if !state.is_answer_visible {
+            state.is_answer_visible = state.quiz.is_none();
+            return;
+        }
+        if state.card_index + 1 >= state.indexes.len() {
+            self.finish_quiz();
+            return;
+        }
+        state.card_index += 1;
+        state.is_answer_visible = false;
+        state.shown_at = Instant::now();
+        self.prepare_quiz();
+    }
+    pub fn finish_quiz(&mut self) {
+        if let Some(state) = &self.study_state
+            && state.quiz_score.1 > 0
+        {
+            let (correct, answers) = state.quiz_score;
+            self.notify(
+                NotificationKind::Info,
+                "Quiz",
+                vec![format!("Poprawne odpowiedzi: {} z {}.", correct, answers)],
+            );
+        }
+        self.stop_studying();
+    }
+    pub fn accept_suggested_rating(&mut self) -> Result<()> {
+        let rating = self.study_state.as_ref().and_then(|state| {
+            match (&state.answer_check, &state.quiz, state.chosen) {
+                (Some(check), _, _) => Some(check.suggested_rating),
+                (None, Some(quiz), Some(choice)) => {
+                    Some(if quiz.is_correct(choice) { 4 } else { 1 })
                 }
-                self.study_state = Some(StudyState {
-                    deck_index: idx,
-                    card_index: 0,
-                    is_answer_visible: false,
-                    indexes: indices,
-                });
-                self.current_screen = CurrentScreen::Studying;
+                _ => None,
             }
+        });
+        match rating {
+            Some(rating) => self.rate_current_card(rating),
+            None => Ok(()),
         }
     }
-
+    pub fn toggle_answer_mode(&mut self) -> Result<()> {
+        if let Some(idx) = self.selected_deck_index() {
+            let change = CollectionChange::begin_cards(&self.collection, idx, &[]);
+            let deck = &mut self.collection.decks[idx];
+            deck.set_answer_mode(deck.get_answer_mode().next());
+            self.message = Some(format!(
+                "Tryb odpowiedzi: {}.",
+                deck.get_answer_mode().label()
+            ));
+            self.storage.save_deck(&self.collection, idx)?;
+            self.push_change("tryb odpowiedzi", change, None);
+        }
+        Ok(())
+    }
     pub fn toggle_answer(&mut self) {
         if let Some(state) = &mut self.study_state {
             state.is_answer_visible = !state.is_answer_visible;
         }
     }
-    pub fn rate_current_card(&mut self, rating: u32) {
-        let today = chrono::Local::now().naive_local().date();
+    pub fn rate_current_card(&mut self, rating: u32) -> Result<()> {
+        let now = chrono::Local::now().naive_local();
+        let study_before = self.study_state.clone();
         if let Some(state) = &mut self.study_state {
-            let idx = state.indexes[state.card_index];
-            if let Some(card) = self.collection.decks[state.deck_index].get_card_mut(idx) {
-                card.review(rating, today)
+            let (deck_idx, idx) = state.indexes[state.card_index];
+            let mut touched = self.collection.decks[deck_idx].get_siblings(idx);
+            touched.push(idx);
+            let change = CollectionChange::begin_cards(&self.collection, deck_idx, &touched);
+            if let Some(entry) =
+                self.collection
+                    .review_card(deck_idx, idx, rating, now, state.shown_at.elapsed())
+            {
+                let entry = entry.clone();
+                self.storage
+                    .save_review(&self.collection, deck_idx, idx, &entry)?;
             }
-            if state.card_index >= state.indexes.len() - 1 {
-                self.stop_studying()
-            } else {
-                state.card_index += 1;
-                state.is_answer_visible = false;
+            let learning = self.collection.decks[deck_idx]
+                .get_card(idx)
+                .is_some_and(|card| card.is_learning());
+            if rating < 3 {
+                let position = (state.card_index + 1 + self.requeue_gap).min(state.indexes.len());
+                state.indexes.insert(position, (deck_idx, idx));
+                if learning {
+                    state.failed.insert((deck_idx, idx));
+                }
+            } else if learning {
+                state.indexes.push((deck_idx, idx));
             }
+            self.next_study_card(now);
+            self.push_change("ocena karty", change, study_before);
         }
+        Ok(())
+    }
+    fn next_study_card(&mut self, now: NaiveDateTime) {
+        let Some(state) = &mut self.study_state else {
+            return;
+        };
+        let decks = &self.collection.decks;
+        let due_at = |&(deck_idx, card_idx): &(usize, usize)| {
+            decks
+                .get(deck_idx)
+                .and_then(|deck| deck.get_card(card_idx))
+                .and_then(|card| card.get_due_at())
+        };
+        let next = state.card_index + 1;
+        let waiting = &state.indexes[next.min(state.indexes.len())..];
+        let ready = waiting
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| Some((i, due_at(item)?)))
+            .filter(|(_, due_at)| *due_at <= now)
+            .min_by_key(|(_, due_at)| *due_at)
+            .map(|(i, _)| i)
+            .or_else(|| {
+                waiting
+                    .iter()
+                    .position(|item| due_at(item).is_none() || state.failed.contains(item))
+            });
+        let Some(i) = ready else {
+            let returns_at = waiting.iter().filter_map(due_at).min();
+            self.stop_studying();
+            if let Some(returns_at) = returns_at {
+                let format = if returns_at.date() == now.date() {
+                    "%H:%M"
+                } else {
+                    "%d.%m %H:%M"
+                };
+                self.message = Some(format!(
+                    "Karty w nauce wrócą o {}.",
+                    returns_at.format(format)
+                ));
+            }
+            return;
+        };
+        let item = state.indexes.remove(next + i);
+        state.failed.remove(&item);
+        state.indexes.insert(next, item);
+        state.card_index = next;
+        state.is_answer_visible = false;
+        state.shown_at = Instant::now();
+        state.typed_answer.clear();
+        state.answer_check = None;
+        self.prepare_quiz();
     }
     pub fn stop_studying(&mut self) {
         self.study_state = None;
         self.current_screen = CurrentScreen::Main;
     }
-    pub fn add_new_deck(&mut self) {
+    pub fn add_new_deck(&mut self) -> Result<()> {
         let path = self.path_input.trim().to_string();
         let name = self.name_input.trim().to_string();
-        if let Ok(deck) =
-            Deck::new_from_file(&path, name.clone(), chrono::Local::now().date_naive())
-        {
-            self.collection.add_deck(deck);
-        }
+        let options = self.options_input.trim().to_string();
         self.name_input.clear();
         self.path_input.clear();
+        self.options_input.clear();
+        self.currently_editing = None;
+        self.current_screen = CurrentScreen::Main;
+        if name.is_empty() {
+            return Err(CollectionError::EmptyDeckName.into());
+        }
+        let options = CsvOptions::parse(&options)?;
+        let change = CollectionChange::begin(&self.collection, &[]);
+        let (added, lines) = self.import_decks(&path, &name, &options)?;
+        self.push_change("import talii", change, None);
+        if added == 0 {
+            self.notify(
+                NotificationKind::Error,
+                "Nie zaimportowano żadnej karty",
+                lines,
+            );
+        } else {
+            self.notify(
+                NotificationKind::Info,
+                &format!("Import talii \"{}\"", name),
+                lines,
+            );
+        }
+        Ok(())
+    }
+    pub fn import_decks(
+        &mut self,
+        path: &str,
+        name: &str,
+        csv_options: &CsvOptions,
+    ) -> Result<(usize, Vec<String>)> {
+        let mut imported =
+            formats::import_file(path, name, csv_options, chrono::Local::now().date_naive())?;
+        self.reassign_taken_ids(&mut imported.decks, &mut imported.review_log);
+        let format = formats::source_format(path);
+        let options = match format {
+            SourceFormat::Anki => String::new(),
+            _ => csv_options.to_spec(),
+        };
+        for deck in imported.decks.iter_mut() {
+            deck.set_source(DeckSource::new(Path::new(path), format, options.clone()));
+        }
+        let mut lines = imported.report.summary_lines();
+        if imported.report.added == 0 {
+            return Ok((0, lines));
+        }
+        if imported.decks.len() > 1 {
+            lines.push(format!("Utworzono talii: {}.", imported.decks.len()));
+        }
+        if !imported.review_log.is_empty() {
+            lines.push(format!(
+                "Zaimportowano wpisów historii powtórek: {}.",
+                imported.review_log.len()
+            ));
+        }
+        for deck in imported.decks {
+            self.collection.add_deck(deck);
+            self.storage
+                .save_deck(&self.collection, self.collection.decks.len() - 1)?;
+        }
+        self.storage
+            .save_reviews(&self.collection, &imported.review_log)?;
+        self.collection.import_reviews(imported.review_log);
+        self.select_deck(self.collection.decks.len() - 1);
+        Ok((imported.report.added, lines))
+    }
+    fn reassign_taken_ids(&self, decks: &mut [Deck], review_log: &mut [ReviewLogEntry]) {
+        let mut seen = HashSet::new();
+        for deck in decks.iter_mut() {
+            if self.collection.decks.iter().any(|d| d.get_id() == deck.id) {
+                deck.id = Uuid::new_v4();
+            }
+            for card in deck.cards.iter_mut() {
+                if self.collection.contains_card(card.id) || !seen.insert(card.id) {
+                    let id = Uuid::new_v4();
+                    for entry in review_log.iter_mut().filter(|e| e.card_id == card.id) {
+                        entry.card_id = id;
+                    }
+                    card.note_id = Some(card.get_note_id());
+                    card.id = id;
+                    seen.insert(id);
+                }
+            }
+        }
+    }
+    pub fn start_update_from_source(&mut self) {
+        if let Some(idx) = self.selected_deck_index() {
+            let input = self.collection.decks[idx]
+                .get_source()
+                .map(|source| source.path.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            self.deck_prompt = Some(DeckPrompt {
+                kind: DeckPromptKind::UpdateFromSource(idx),
+                input,
+                error: None,
+            });
+            self.current_screen = CurrentScreen::DeckPrompt;
+        }
+    }
+    pub fn reload_from_source(&mut self) -> Result<()> {
+        let Some(idx) = self.selected_deck_index() else {
+            return Ok(());
+        };
+        let Some(source) = self.collection.decks[idx].get_source() else {
+            self.start_update_from_source();
+            return Ok(());
+        };
+        let path = source.path.to_string_lossy().into_owned();
+        self.update_preview = Some(self.prepare_update(idx, &path)?);
+        self.current_screen = CurrentScreen::UpdatePreview;
+        Ok(())
+    }
+    fn prepare_update(&self, deck_index: usize, path: &str) -> Result<UpdatePreview> {
+        let deck = &self.collection.decks[deck_index];
+        let options = match deck.get_source() {
+            Some(source)
+                if fs::canonicalize(path).is_ok_and(|p| p == source.path)
+                    && source.format == formats::source_format(path) =>
+            {
+                source.options.clone()
+            }
+            _ => String::new(),
+        };
+        let imported = formats::import_file(
+            path,
+            &deck.get_name(),
+            &CsvOptions::parse(&options)?,
+            chrono::Local::now().date_naive(),
+        )?;
+        let same_name = imported
+            .decks
+            .iter()
+            .position(|d| d.get_name() == deck.get_name());
+        let mut incoming: Vec<FlashCard> = match same_name {
+            Some(i) if imported.decks.len() > 1 => imported.decks.into_iter().nth(i).unwrap().cards,
+            _ => imported
+                .decks
+                .into_iter()
+                .flat_map(|deck| deck.cards)
+                .collect(),
+        };
+        for card in incoming.iter_mut() {
+            if deck.get_card_index(card.id).is_none() && self.collection.contains_card(card.id) {
+                card.note_id = Some(card.get_note_id());
+                card.id = Uuid::new_v4();
+            }
+        }
+        Ok(UpdatePreview {
+            deck_index,
+            source: path.to_string(),
+            options,
+            update: DeckUpdate::compute(deck, incoming),
+            retire_removed: false,
+            scroll: 0,
+        })
+    }
+    pub fn check_sources(&mut self) {
+        if !self.watch_sources
+            || self.current_screen != CurrentScreen::Main
+            || !self.notifications.is_empty()
+        {
+            return;
+        }
+        if let Some(idx) = self
+            .collection
+            .decks
+            .iter()
+            .position(|deck| deck.get_source().is_some_and(|source| source.has_changed()))
+        {
+            self.source_change = Some(idx);
+            self.current_screen = CurrentScreen::SourceChanged;
+        }
+    }
+    pub fn accept_source_change(&mut self) -> Result<()> {
+        self.current_screen = CurrentScreen::Main;
+        let Some(idx) = self.source_change.take() else {
+            return Ok(());
+        };
+        let Some(source) = self.collection.decks[idx].get_source() else {
+            return Ok(());
+        };
+        let path = source.path.to_string_lossy().into_owned();
+        self.select_deck(idx);
+        match self.prepare_update(idx, &path) {
+            Ok(preview) => {
+                self.update_preview = Some(preview);
+                self.current_screen = CurrentScreen::UpdatePreview;
+            }
+            Err(error) => {
+                self.skip_source_change(idx)?;
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+    pub fn decline_source_change(&mut self) -> Result<()> {
+        self.current_screen = CurrentScreen::Main;
+        match self.source_change.take() {
+            Some(idx) => self.skip_source_change(idx),
+            None => Ok(()),
+        }
+    }
+    fn skip_source_change(&mut self, deck_index: usize) -> Result<()> {
+        if let Some(source) = &mut self.collection.decks[deck_index].source {
+            source.modified_at = source.current_modified();
+            self.storage.save_deck(&self.collection, deck_index)?;
+        }
+        Ok(())
+    }
+    pub fn toggle_retire_removed(&mut self) {
+        if let Some(preview) = &mut self.update_preview {
+            preview.retire_removed = !preview.retire_removed;
+        }
+    }
+    pub fn scroll_update_preview(&mut self, down: bool) {
+        if let Some(preview) = &mut self.update_preview {
+            preview.scroll = if down {
+                preview.scroll.saturating_add(1)
+            } else {
+                preview.scroll.saturating_sub(1)
+            };
+        }
+    }
+    pub fn cancel_update(&mut self) {
+        self.update_preview = None;
+        self.current_screen = CurrentScreen::Main;
+    }
+    pub fn apply_update(&mut self) -> Result<()> {
+        let Some(preview) = self.update_preview.take() else {
+            return Ok(());
+        };
+        self.current_screen = CurrentScreen::Main;
+        let update = preview.update;
+        let mut lines = vec![
+            format!("Nowe karty: {}.", update.added.len()),
+            format!("Zmienione karty: {}.", update.changed.len()),
+        ];
+        if preview.retire_removed {
+            lines.push(format!("Usunięte karty: {}.", update.removed.len()));
+        } else if !update.removed.is_empty() {
+            lines.push(format!(
+                "Karty spoza źródła pozostawione w talii: {}.",
+                update.removed.len()
+            ));
+        }
+        let change = CollectionChange::begin(&self.collection, &[preview.deck_index]);
+        let deck = &mut self.collection.decks[preview.deck_index];
+        deck.apply_update(update, preview.retire_removed);
+        deck.set_source(DeckSource::new(
+            Path::new(&preview.source),
+            formats::source_format(&preview.source),
+            preview.options,
+        ));
+        let title = format!("Aktualizacja talii \"{}\"", deck.get_name());
+        self.storage
+            .save_deck(&self.collection, preview.deck_index)?;
+        self.push_change("aktualizacja talii", change, None);
+        self.notify(NotificationKind::Info, &title, lines);
+        Ok(())
+    }
+    pub fn notify(&mut self, kind: NotificationKind, title: &str, lines: Vec<String>) {
+        self.notifications.push(Notification {
+            kind,
+            title: title.to_string(),
+            lines,
+        });
+    }
+    pub fn notify_error(&mut self, title: &str, error: &anyhow::Error) {
+        let lines = error
+            .chain()
+            .enumerate()
+            .map(|(i, cause)| {
+                if i == 0 {
+                    cause.to_string()
+                } else {
+                    format!("Przyczyna: {}", cause)
+                }
+            })
+            .collect();
+        self.notify(NotificationKind::Error, title, lines);
+    }
+    pub fn dismiss_notification(&mut self) {
+        if !self.notifications.is_empty() {
+            self.notifications.remove(0);
+        }
+    }
+    pub fn open_browser(&mut self, whole_collection: bool) {
+        let deck_index = if whole_collection {
+            None
+        } else {
+            match self.selected_deck_index() {
+                Some(idx) => Some(idx),
+                None => return,
+            }
+        };
+        let mut table_state = TableState::default();
+        table_state.select(Some(0));
+        self.browser_state = Some(BrowserState {
+            deck_index,
+            query: String::new(),
+            is_searching: false,
+            sort_column: BrowserColumn::Due,
+            sort_ascending: true,
+            filter: CardFilter::All,
+            table_state,
+            confirm_delete: false,
+            marked: HashSet::new(),
+        });
+        self.current_screen = CurrentScreen::Browsing;
+    }
+    pub fn close_browser(&mut self) {
+        self.browser_state = None;
+        self.current_screen = CurrentScreen::Main;
+    }
+    pub fn browser_rows(&self) -> Vec<(usize, usize)> {
+        let Some(state) = &self.browser_state else {
+            return vec![];
+        };
+        let now = chrono::Local::now().naive_local();
+        let query = state.query.to_lowercase();
+        let mut rows: Vec<(usize, usize)> = self
+            .collection
+            .decks
+            .iter()
+            .enumerate()
+            .filter(|(deck_idx, _)| state.deck_index.is_none_or(|idx| idx == *deck_idx))
+            .flat_map(|(deck_idx, deck)| {
+                (0..deck.get_card_count()).map(move |card_idx| (deck_idx, card_idx))
+            })
+            .filter(|(deck_idx, card_idx)| {
+                let card = self.card_at(*deck_idx, *card_idx);
+                state.filter.matches(card, now)
+                    && (query.is_empty()
+                        || card.get_question().to_lowercase().contains(&query)
+                        || card.get_answer().to_lowercase().contains(&query)
+                        || card.has_tag(&query))
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            let ordering = state
+                .sort_column
+                .compare(self.card_at(a.0, a.1), self.card_at(b.0, b.1));
+            if state.sort_ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+        rows
+    }
+    fn card_at(&self, deck_index: usize, card_index: usize) -> &FlashCard {
+        &self.collection.decks[deck_index].cards[card_index]
+    }
+    pub fn browser_select_next(&mut self) {
+        if let Some(state) = &mut self.browser_state {
+            state.table_state.select_next();
+        }
+    }
+    pub fn browser_select_previous(&mut self) {
+        if let Some(state) = &mut self.browser_state {
+            state.table_state.select_previous();
+        }
+    }
+    pub fn browser_sort_by(&mut self, column: BrowserColumn) {
+        if let Some(state) = &mut self.browser_state {
+            state.sort_column = column;
+        }
+    }
+    pub fn browser_toggle_sort_direction(&mut self) {
+        if let Some(state) = &mut self.browser_state {
+            state.sort_ascending = !state.sort_ascending;
+        }
+    }
+    pub fn browser_cycle_filter(&mut self) {
+        if let Some(state) = &mut self.browser_state {
+            state.filter = state.filter.next();
+            state.table_state.select(Some(0));
+        }
+    }
+    pub fn browser_edit_query(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(state) = &mut self.browser_state {
+            edit(&mut state.query);
+            state.table_state.select(Some(0));
+        }
+    }
+    pub fn browser_selected_card(&self) -> Option<(usize, usize)> {
+        let selected = self.browser_state.as_ref()?.table_state.selected()?;
+        self.browser_rows().get(selected).copied()
+    }
+    pub fn browser_request_delete(&mut self) {
+        if self.browser_selected_card().is_some()
+            && let Some(state) = &mut self.browser_state
+        {
+            state.confirm_delete = true;
+        }
+    }
+    pub fn browser_cancel_delete(&mut self) {
+        if let Some(state) = &mut self.browser_state {
+            state.confirm_delete = false;
+        }
+    }
+    pub fn browser_confirm_delete(&mut self) -> Result<()> {
+        self.browser_cancel_delete();
+        if let Some((deck_idx, card_idx)) = self.browser_selected_card() {
+            let change = CollectionChange::begin(&self.collection, &[deck_idx]);
+            self.collection.decks[deck_idx].remove_card(card_idx);
+            self.storage.save_deck(&self.collection, deck_idx)?;
+            self.push_change("usunięcie karty", change, None);
+        }
+        Ok(())
+    }
+    pub fn open_editor(&mut self, deck_index: usize, card_index: Option<usize>) {
+        let Some(deck) = self.collection.decks.get(deck_index) else {
+            return;
+        };
+        let card = card_index.and_then(|idx| deck.get_card(idx));
+        self.editor_state = Some(EditorState {
+            deck_index,
+            card_index: card.and(card_index),
+            question: card.map(|c| c.get_question()).unwrap_or_default(),
+            answer: card.map(|c| c.get_answer()).unwrap_or_default(),
+            tags: card.map(|c| c.get_tags().join(" ")).unwrap_or_default(),
+            direction: card_index
+                .map(|idx| deck.get_note_direction(idx))
+                .unwrap_or_default(),
+            focus: EditorField::Question,
+            error: None,
+            return_screen: self.current_screen,
+        });
+        self.current_screen = CurrentScreen::EditingCard;
+    }
+    pub fn open_editor_from_browser(&mut self, new_card: bool) {
+        let selected = self.browser_selected_card();
+        let deck_index = self
+            .browser_state
+            .as_ref()
+            .and_then(|state| state.deck_index)
+            .or(selected.map(|(deck_idx, _)| deck_idx));
+        match (deck_index, selected) {
+            (Some(deck_idx), _) if new_card => self.open_editor(deck_idx, None),
+            (_, Some((deck_idx, card_idx))) => self.open_editor(deck_idx, Some(card_idx)),
+            _ => {}
+        }
+    }
+    pub fn open_editor_from_main(&mut self) {
+        if let Some(idx) = self.selected_deck_index() {
+            self.open_editor(idx, None);
+        }
+    }
+    pub fn editor_toggle_focus(&mut self) {
+        if let Some(state) = &mut self.editor_state {
+            state.focus = match state.focus {
+                EditorField::Question => EditorField::Answer,
+                EditorField::Answer => EditorField::Tags,
+                EditorField::Tags => EditorField::Question,
+            };
+        }
+    }
+    pub fn editor_cycle_direction(&mut self) {
+        if let Some(state) = &mut self.editor_state {
+            state.direction = state.direction.next();
+        }
+    }
+    pub fn editor_edit(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(state) = &mut self.editor_state {
+            match state.focus {
+                EditorField::Question => edit(&mut state.question),
+                EditorField::Answer => edit(&mut state.answer),
+                EditorField::Tags => {
+                    edit(&mut state.tags);
+                    state.tags.retain(|c| c != '\n');
+                }
+            }
+            state.error = None;
+        }
+    }
+    pub fn close_editor(&mut self) {
+        if let Some(state) = self.editor_state.take() {
+            self.current_screen = state.return_screen;
+        }
+    }
+    pub fn save_editor(&mut self) -> Result<()> {
+        let Some(state) = &mut self.editor_state else {
+            return Ok(());
+        };
+        let change = CollectionChange::begin(&self.collection, &[state.deck_index]);
+        let label = match state.card_index {
+            Some(_) => "edycja karty",
+            None => "nowa karta",
+        };
+        let deck = &mut self.collection.decks[state.deck_index];
+        let question = state.question.trim_end().to_string();
+        let answer = state.answer.trim_end().to_string();
+        let today = chrono::Local::now().date_naive();
+        let result = match state.card_index {
+            Some(card_idx) => {
+                let id = deck.cards[card_idx].get_id();
+                deck.update_card(card_idx, question, answer, today)
+                    .and_then(|()| match deck.get_card_index(id) {
+                        Some(card_idx) => {
+                            deck.set_note_tags(card_idx, &state.tags);
+                            deck.set_note_direction(card_idx, state.direction, today)
+                        }
+                        None => Ok(()),
+                    })
+            }
+            None => FlashCard::new_note(question, answer, state.direction, today).map(|cards| {
+                for mut card in cards {
+                    card.set_tags(state.tags.split([' ', ',', ';']));
+                    deck.add_card(card);
+                }
+            }),
+        };
+        if let Err(error) = result {
+            state.error = Some(error.to_string());
+            change.rollback(&mut self.collection);
+            return Ok(());
+        }
+        let deck_index = state.deck_index;
+        self.close_editor();
+        self.storage.save_deck(&self.collection, deck_index)?;
+        self.push_change(label, change, None);
+        Ok(())
+    }
+    pub fn browser_toggle_mark(&mut self) {
+        if let Some((deck_idx, card_idx)) = self.browser_selected_card()
+            && let Some(card) = self.collection.decks[deck_idx].get_card(card_idx)
+            && let Some(state) = &mut self.browser_state
+        {
+            let id = card.get_id();
+            if !state.marked.remove(&id) {
+                state.marked.insert(id);
+            }
+        }
+    }
+    pub fn browser_cycle_direction(&mut self) -> Result<()> {
+        let Some((deck_idx, card_idx)) = self.browser_selected_card() else {
+            return Ok(());
+        };
+        let Some(state) = &self.browser_state else {
+            return Ok(());
+        };
+        let direction = self.collection.decks[deck_idx]
+            .get_note_direction(card_idx)
+            .next();
+        let mut ids: Vec<Uuid> = state.marked.iter().copied().collect();
+        if ids.is_empty() {
+            ids.push(self.card_at(deck_idx, card_idx).get_id());
+        }
+        let today = chrono::Local::now().date_naive();
+        let all_decks: Vec<usize> = (0..self.collection.decks.len()).collect();
+        let change = CollectionChange::begin(&self.collection, &all_decks);
+        let mut changed = HashSet::new();
+        let mut result = Ok(());
+        'cards: for id in ids {
+            for (deck_idx, deck) in self.collection.decks.iter_mut().enumerate() {
+                if let Some(card_idx) = deck.get_card_index(id) {
+                    result = deck.set_note_direction(card_idx, direction, today);
+                    if result.is_err() {
+                        break 'cards;
+                    }
+                    changed.insert(deck_idx);
+                }
+            }
+        }
+        if let Err(error) = result {
+            change.rollback(&mut self.collection);
+            self.message = Some(error.to_string());
+            return Ok(());
+        }
+        for deck_idx in changed {
+            self.storage.save_deck(&self.collection, deck_idx)?;
+        }
+        self.push_change("zmiana kierunku kart", change, None);
+        self.message = Some(format!("Kierunek kart: {}.", direction.label()));
+        Ok(())
+    }
+    pub fn start_split(&mut self) {
+        let Some(state) = &self.browser_state else {
+            return;
+        };
+        let Some(deck_index) = state.deck_index else {
+            self.message = Some("Podział talii jest dostępny tylko w widoku jednej talii.".into());
+            return;
+        };
+        if state.marked.is_empty() {
+            self.message = Some("Zaznacz karty spacją, aby przenieść je do nowej talii.".into());
+            return;
+        }
+        self.deck_prompt = Some(DeckPrompt {
+            kind: DeckPromptKind::Split {
+                deck_index,
+                card_ids: state.marked.iter().copied().collect(),
+            },
+            input: String::new(),
+            error: None,
+        });
+        self.browser_state = None;
+        self.current_screen = CurrentScreen::DeckPrompt;
+    }
+    pub fn start_rename(&mut self) {
+        if let Some(idx) = self.selected_deck_index() {
+            self.deck_prompt = Some(DeckPrompt {
+                kind: DeckPromptKind::Rename(idx),
+                input: self.collection.decks[idx].get_name(),
+                error: None,
+            });
+            self.current_screen = CurrentScreen::DeckPrompt;
+        }
+    }
+    pub fn start_limits(&mut self) {
+        if let Some(idx) = self.selected_deck_index() {
+            self.deck_prompt = Some(DeckPrompt {
+                kind: DeckPromptKind::Limits(idx),
+                input: self.collection.decks[idx].get_limits().to_string(),
+                error: None,
+            });
+            self.current_screen = CurrentScreen::DeckPrompt;
+        }
+    }
+    pub fn start_learning_steps(&mut self) {
+        if let Some(idx) = self.selected_deck_index() {
+            self.deck_prompt = Some(DeckPrompt {
+                kind: DeckPromptKind::LearningSteps(idx),
+                input: self.collection.decks[idx].get_learning_steps().to_string(),
+                error: None,
+            });
+            self.current_screen = CurrentScreen::DeckPrompt;
+        }
+    }
+    pub fn start_export(&mut self, whole_collection: bool) {
+        let deck_index = if whole_collection {
+            None
+        } else {
+            match self.selected_deck_index() {
+                Some(idx) => Some(idx),
+                None => return,
+            }
+        };
+        let name = match deck_index {
+            Some(idx) => self.collection.decks[idx].get_name(),
+            None => "kolekcja".to_string(),
+        };
+        let format = ExportFormat::SimpleJson;
+        self.deck_prompt = Some(DeckPrompt {
+            kind: DeckPromptKind::Export { deck_index, format },
+            input: format!("{}.{}", name, format.extension()),
+            error: None,
+        });
+        self.current_screen = CurrentScreen::DeckPrompt;
+    }
+    pub fn cycle_export_format(&mut self) {
+        if let Some(prompt) = &mut self.deck_prompt
+            && let DeckPromptKind::Export { format, .. } = &mut prompt.kind
+        {
+            let stem = prompt
+                .input
+                .strip_suffix(&format!(".{}", format.extension()))
+                .unwrap_or(&prompt.input)
+                .to_string();
+            *format = format.next();
+            prompt.input = format!("{}.{}", stem, format.extension());
+            prompt.error = None;
+        }
+    }
+    pub fn prompt_edit(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(prompt) = &mut self.deck_prompt {
+            edit(&mut prompt.input);
+            prompt.error = None;
+        }
+    }
+    pub fn cancel_prompt(&mut self) {
+        self.deck_prompt = None;
+        self.current_screen = CurrentScreen::Main;
+    }
+    pub fn submit_prompt(&mut self) -> Result<()> {
+        let Some(prompt) = &mut self.deck_prompt else {
+            return Ok(());
+        };
+        match &prompt.kind {
+            DeckPromptKind::Rename(idx) => {
+                let idx = *idx;
+                let renamed = self.collection.deck_with_descendants(idx);
+                let change = CollectionChange::begin(&self.collection, &renamed);
+                if let Err(error) = self.collection.rename_deck(idx, &prompt.input) {
+                    prompt.error = Some(error.to_string());
+                    return Ok(());
+                }
+                for i in renamed {
+                    self.storage.save_deck(&self.collection, i)?;
+                }
+                self.push_change("zmiana nazwy talii", change, None);
+                self.select_deck(idx);
+            }
+            DeckPromptKind::Export { deck_index, format } => {
+                let path = prompt.input.trim().to_string();
+                match formats::export(&self.collection, *deck_index, *format, Path::new(&path)) {
+                    Ok(count) => {
+                        let lines = vec![format!("Zapisano kart: {} do pliku {}.", count, path)];
+                        self.notify(NotificationKind::Info, "Eksport", lines);
+                    }
+                    Err(error) => {
+                        prompt.error = Some(format!("{:#}", error));
+                        return Ok(());
+                    }
+                }
+            }
+            DeckPromptKind::UpdateFromSource(idx) => {
+                let path = prompt.input.trim().to_string();
+                let deck_index = *idx;
+                match self.prepare_update(deck_index, &path) {
+                    Ok(preview) => {
+                        self.deck_prompt = None;
+                        self.update_preview = Some(preview);
+                        self.current_screen = CurrentScreen::UpdatePreview;
+                    }
+                    Err(error) => {
+                        if let Some(prompt) = &mut self.deck_prompt {
+                            prompt.error = Some(format!("{:#}", error));
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            DeckPromptKind::Split {
+                deck_index,
+                card_ids,
+            } => {
+                let deck_index = *deck_index;
+                let change = CollectionChange::begin(&self.collection, &[deck_index]);
+                match self
+                    .collection
+                    .split_deck(deck_index, card_ids, &prompt.input)
+                {
+                    Ok(new_index) => {
+                        self.storage.save_deck(&self.collection, new_index)?;
+                        self.storage.save_deck(&self.collection, deck_index)?;
+                        self.storage.save_deck_order(&self.collection)?;
+                        self.push_change("podział talii", change, None);
+                        self.select_deck(new_index);
+                    }
+                    Err(error) => {
+                        prompt.error = Some(error.to_string());
+                        return Ok(());
+                    }
+                }
+            }
+            DeckPromptKind::Limits(idx) => {
+                let idx = *idx;
+                match prompt.input.parse::<DeckLimits>() {
+                    Ok(limits) => {
+                        let change = CollectionChange::begin_cards(&self.collection, idx, &[]);
+                        self.collection.decks[idx].set_limits(limits);
+                        self.storage.save_deck(&self.collection, idx)?;
+                        self.push_change("limity dzienne", change, None);
+                    }
+                    Err(error) => {
+                        prompt.error = Some(error.to_string());
+                        return Ok(());
+                    }
+                }
+            }
+            DeckPromptKind::LearningSteps(idx) => {
+                let idx = *idx;
+                match prompt.input.parse::<LearningSteps>() {
+                    Ok(steps) => {
+                        let change = CollectionChange::begin_cards(&self.collection, idx, &[]);
+                        self.collection.decks[idx].set_learning_steps(steps);
+                        self.storage.save_deck(&self.collection, idx)?;
+                        self.push_change("kroki nauki", change, None);
+                    }
+                    Err(error) => {
+                        prompt.error = Some(error.to_string());
+                        return Ok(());
+                    }
+                }
+            }
+            DeckPromptKind::TagFilter => {
+                let text = prompt.input.trim().to_string();
+                if text.is_empty() {
+                    self.tag_filter = None;
+                } else {
+                    match text.parse::<TagExpr>() {
+                        Ok(expr) => self.tag_filter = Some(TagFilter { text, expr }),
+                        Err(error) => {
+                            prompt.error = Some(error.to_string());
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+        self.cancel_prompt();
+        Ok(())
+    }
+    pub fn start_delete_deck(&mut self) {
+        if self.selected_deck_index().is_some() {
+            self.current_screen = CurrentScreen::ConfirmingDeckDeletion;
+        }
+    }
+    pub fn confirm_delete_deck(&mut self) -> Result<()> {
+        self.current_screen = CurrentScreen::Main;
+        if let Some(idx) = self.selected_deck_index() {
+            let change = CollectionChange::begin(&self.collection, &[idx]);
+            if let Some(deck) = self.collection.remove_deck(idx) {
+                self.storage.remove_deck(&self.collection, deck.get_id())?;
+                self.push_change("usunięcie talii", change, None);
+                self.merge_source = None;
+                self.clamp_deck_selection();
+            }
+        }
+        Ok(())
+    }
+    fn clamp_deck_selection(&mut self) {
+        let len = self.deck_rows().len();
+        if len == 0 {
+            self.deck_list_state.select(None);
+        } else if self.deck_list_state.selected().is_none_or(|idx| idx >= len) {
+            self.deck_list_state.select(Some(len - 1));
+        }
+    }
+    pub fn move_selected_deck(&mut self, up: bool) -> Result<()> {
+        let Some(row) = self.selected_deck_row() else {
+            return Ok(());
+        };
+        let all_decks: Vec<usize> = (0..self.collection.decks.len()).collect();
+        let change = CollectionChange::begin(&self.collection, &all_decks);
+        if self.collection.move_deck(&row.path, up) {
+            self.select_deck_path(&row.path);
+            self.merge_source = None;
+            self.storage.save_deck_order(&self.collection)?;
+            self.push_change("przeniesienie talii", change, None);
+        }
+        Ok(())
+    }
+    pub fn toggle_merge(&mut self) -> Result<()> {
+        let Some(target) = self.selected_deck_index() else {
+            return Ok(());
+        };
+        let Some(source_id) = self.merge_source.take() else {
+            self.merge_source = Some(self.collection.decks[target].get_id());
+            return Ok(());
+        };
+        let Some(source) = self
+            .collection
+            .decks
+            .iter()
+            .position(|deck| deck.get_id() == source_id)
+        else {
+            return Ok(());
+        };
+        let change = CollectionChange::begin(&self.collection, &[source, target]);
+        match self.collection.merge_decks(source, target) {
+            Ok(new_target) => {
+                self.storage.save_deck(&self.collection, new_target)?;
+                self.storage.remove_deck(&self.collection, source_id)?;
+                self.push_change("scalenie talii", change, None);
+                self.select_deck(new_target);
+            }
+            Err(error) => self.notify_error("Nie udało się scalić talii", &error.into()),
+        }
+        Ok(())
+    }
+    fn push_change(
+        &mut self,
+        label: &'static str,
+        mut change: CollectionChange,
+        study_before: Option<StudyState>,
+    ) {
+        change.finish(&self.collection);
+        if change.is_empty() {
+            return;
+        }
+        let study_after = match study_before {
+            Some(_) => self.study_state.clone(),
+            None => None,
+        };
+        self.undo_stack.push(HistoryEntry {
+            label,
+            change,
+            study_before,
+            study_after,
+        });
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+    pub fn undo(&mut self) -> Result<()> {
+        self.step_history(true)
+    }
+    pub fn redo(&mut self) -> Result<()> {
+        self.step_history(false)
+    }
+    fn step_history(&mut self, undo: bool) -> Result<()> {
+        let stack = if undo {
+            &mut self.undo_stack
+        } else {
+            &mut self.redo_stack
+        };
+        let Some(mut entry) = stack.pop() else {
+            self.message = Some(
+                if undo {
+                    "Nie ma czego cofnąć."
+                } else {
+                    "Nie ma czego ponowić."
+                }
+                .to_string(),
+            );
+            return Ok(());
+        };
+        let session = self.study_state.as_ref().map(|state| state.session);
+        let entry_session = entry.study_before.as_ref().map(|state| state.session);
+        if session.is_some() && entry_session != session {
+            let action = if undo { "cofnąć" } else { "ponowić" };
+            self.message = Some(format!("Zakończ naukę, aby {}: {}.", action, entry.label));
+            stack.push(entry);
+            return Ok(());
+        }
+        let reviews = if undo {
+            let now = chrono::Local::now().naive_local();
+            entry.change.undo(&mut self.collection, now)
+        } else {
+            entry.change.redo(&mut self.collection)
+        };
+        for id in entry.change.get_deck_ids() {
+            match self.collection.decks.iter().position(|d| d.get_id() == id) {
+                Some(idx) => self.storage.save_deck(&self.collection, idx)?,
+                None => self.storage.remove_deck(&self.collection, id)?,
+            }
+        }
+        self.storage.save_deck_order(&self.collection)?;
+        self.storage.save_reviews(&self.collection, &reviews)?;
+        if entry_session.is_some() && entry_session == self.last_session {
+            let study = if undo {
+                &entry.study_before
+            } else {
+                &entry.study_after
+            };
+            match study.clone() {
+                Some(state) => {
+                    self.study_state = Some(StudyState {
+                        is_answer_visible: false,
+                        shown_at: Instant::now(),
+                        typed_answer: String::new(),
+                        answer_check: None,
+                        ..state
+                    });
+                    self.current_screen = CurrentScreen::Studying;
+                    self.prepare_quiz();
+                }
+                None => self.stop_studying(),
+            }
+        }
+        self.merge_source = None;
+        self.clamp_deck_selection();
+        self.message = Some(if undo {
+            format!("Cofnięto: {}.", entry.label)
+        } else {
+            format!("Ponowiono: {}.", entry.label)
+        });
+        if undo {
+            self.redo_stack.push(entry);
+        } else {
+            self.undo_stack.push(entry);
+        }
+        Ok(())
+    }
+    pub fn cancel_merge(&mut self) {
+        self.merge_source = None;
+    }
+    pub fn open_statistics(&mut self) {
+        self.statistics_deck = self.selected_deck_index();
+        self.current_screen = CurrentScreen::Statistics;
+    }
+    pub fn toggle_statistics_scope(&mut self) {
+        self.statistics_deck = match self.statistics_deck {
+            Some(_) => None,
+            None => self.selected_deck_index(),
+        };
+    }
+    pub fn close_statistics(&mut self) {
         self.current_screen = CurrentScreen::Main;
     }
 }
@@ -0,0 +1,143 @@
+use crate::app::DEFAULT_REQUEUE_GAP;
+use crate::formats::{Column, CsvOptions, ExportFormat, parse_delimiter};
+use crate::model::CardDirection;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "flashcards-tui", version, about = "Nauka fiszek w terminalu")]
+pub struct Cli {
+    #[arg(
+        long,
+        value_name = "KATALOG",
+        help = "Katalog z danymi (domyślnie $FLASHCARDS_HOME lub $XDG_DATA_HOME/flashcards-tui)"
+    )]
+    pub data_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Obserwuj pliki źródłowe talii i proponuj wczytanie zmian"
+    )]
+    pub watch: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_REQUEUE_GAP,
+        help = "Po ilu kolejnych kartach pokazać ponownie kartę ocenioną na 1 lub 2"
+    )]
+    pub requeue_gap: usize,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(
+        about = "Importuje talię z pliku JSON, CSV/TSV lub .apkg bez uruchamiania interfejsu"
+    )]
+    Import(ImportArgs),
+    #[command(about = "Eksportuje talię lub całą kolekcję do pliku")]
+    Export(ExportArgs),
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    #[arg(value_name = "PLIK")]
+    pub path: PathBuf,
+    #[arg(
+        long,
+        short,
+        value_name = "NAZWA",
+        help = "Nazwa nowej talii (domyślnie nazwa pliku)"
+    )]
+    pub name: Option<String>,
+    #[arg(
+        long,
+        value_name = "ZNAK",
+        value_parser = parse_delimiter_arg,
+        help = "Separator kolumn, np. ';' lub 'tab' (domyślnie wykrywany)"
+    )]
+    pub delimiter: Option<u8>,
+    #[arg(long, help = "Traktuj cudzysłowy jak zwykłe znaki")]
+    pub no_quoting: bool,
+    #[arg(long, help = "Pierwszy wiersz jest nagłówkiem")]
+    pub header: bool,
+    #[arg(
+        long,
+        conflicts_with = "header",
+        help = "Pierwszy wiersz zawiera już karty"
+    )]
+    pub no_header: bool,
+    #[arg(
+        long,
+        value_name = "KOLUMNA",
+        help = "Kolumna z pytaniem: numer od 1 lub nazwa z nagłówka"
+    )]
+    pub question: Option<Column>,
+    #[arg(
+        long,
+        value_name = "KOLUMNA",
+        help = "Kolumna z odpowiedzią: numer od 1 lub nazwa z nagłówka"
+    )]
+    pub answer: Option<Column>,
+    #[arg(
+        long,
+        value_name = "KOLUMNA",
+        help = "Kolumna z tagami rozdzielonymi spacją lub przecinkiem"
+    )]
+    pub tags: Option<Column>,
+    #[arg(
+        long,
+        value_name = "KIERUNEK",
+        help = "Karty tworzone z każdego wpisu: forward, reverse lub both (domyślnie forward)"
+    )]
+    pub cards: Option<CardDirection>,
+}
+impl ImportArgs {
+    pub fn deck_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+    pub fn csv_options(&self) -> CsvOptions {
+        CsvOptions {
+            delimiter: self.delimiter,
+            quoting: !self.no_quoting,
+            header: match (self.header, self.no_header) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+            question: self.question.clone(),
+            answer: self.answer.clone(),
+            tags: self.tags.clone(),
+            direction: self.cards.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[arg(value_name = "PLIK")]
+    pub path: PathBuf,
+    #[arg(
+        long,
+        short,
+        value_name = "NAZWA",
+        help = "Nazwa eksportowanej talii (domyślnie cała kolekcja)"
+    )]
+    pub deck: Option<String>,
+    #[arg(
+        long,
+        short,
+        value_name = "FORMAT",
+        help = "json, full-json, csv, markdown lub apkg (domyślnie według rozszerzenia pliku)"
+    )]
+    pub format: Option<ExportFormat>,
+}
+
+fn parse_delimiter_arg(value: &str) -> Result<u8, String> {
+    parse_delimiter(value).ok_or_else(|| format!("nieprawidłowy separator: {}", value))
+}
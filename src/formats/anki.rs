@@ -0,0 +1,920 @@
+use super::Imported;
+use crate::model::{
+    CardDirection, Deck, FlashCard, ImportReport, ReviewLogEntry, cloze_segments, normalize_tag,
+};
+use crate::storage::write_atomic;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use rusqlite::{Connection, params};
+use serde_json::{Value, json};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zip::ZipArchive;
+use zip::write::SimpleFileOptions;
+
+const FIELD_SEPARATOR: char = '\x1f';
+const CARD_TYPE_NEW: i64 = 0;
+const CARD_TYPE_REVIEW: i64 = 2;
+const MODEL_TYPE_CLOZE: i64 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE col (id integer primary key, crt integer not null, mod integer not null,
+        scm integer not null, ver integer not null, dty integer not null, usn integer not null,
+        ls integer not null, conf text not null, models text not null, decks text not null,
+        dconf text not null, tags text not null);
+    CREATE TABLE notes (id integer primary key, guid text not null, mid integer not null,
+        mod integer not null, usn integer not null, tags text not null, flds text not null,
+        sfld integer not null, csum integer not null, flags integer not null, data text not null);
+    CREATE TABLE cards (id integer primary key, nid integer not null, did integer not null,
+        ord integer not null, mod integer not null, usn integer not null, type integer not null,
+        queue integer not null, due integer not null, ivl integer not null,
+        factor integer not null, reps integer not null, lapses integer not null,
+        left integer not null, odue integer not null, odid integer not null,
+        flags integer not null, data text not null);
+    CREATE TABLE revlog (id integer primary key, cid integer not null, usn integer not null,
+        ease integer not null, ivl integer not null, lastIvl integer not null,
+        factor integer not null, time integer not null, type integer not null);
+    CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+    CREATE INDEX ix_notes_usn on notes (usn);
+    CREATE INDEX ix_cards_usn on cards (usn);
+    CREATE INDEX ix_revlog_usn on revlog (usn);
+    CREATE INDEX ix_cards_nid on cards (nid);
+    CREATE INDEX ix_cards_sched on cards (did, queue, due);
+    CREATE INDEX ix_revlog_cid on revlog (cid);
+    CREATE INDEX ix_notes_csum on notes (csum);
+";
+
+struct TempFile(PathBuf);
+impl TempFile {
+    fn new() -> TempFile {
+        TempFile(std::env::temp_dir().join(format!("flashcards-anki-{}.db", Uuid::new_v4())))
+    }
+}
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+struct Model {
+    kind: i64,
+    fields: Vec<String>,
+    templates: Vec<(String, String)>,
+}
+
+pub fn is_anki_package(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()),
+        Some(ext) if ext == "apkg" || ext == "colpkg"
+    )
+}
+
+pub fn import(path: &Path, name: &str, today: NaiveDate) -> Result<Imported> {
+    let file = File::open(path)
+        .with_context(|| format!("Nie udało się otworzyć pliku: {}", path.display()))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("Plik nie jest poprawnym archiwum Anki: {}", path.display()))?;
+    let entry_name = if archive.by_name("collection.anki21").is_ok() {
+        "collection.anki21"
+    } else if archive.by_name("collection.anki21b").is_ok() {
+        bail!(
+            "Archiwum zapisano w nowym formacie Anki (collection.anki21b). \
+             Wyeksportuj talię z opcją \"Support older Anki versions\"."
+        );
+    } else if archive.by_name("collection.anki2").is_ok() {
+        "collection.anki2"
+    } else {
+        bail!("Archiwum nie zawiera kolekcji Anki.");
+    };
+    let temp = TempFile::new();
+    {
+        let mut entry = archive.by_name(entry_name)?;
+        let mut out = File::create(&temp.0)?;
+        std::io::copy(&mut entry, &mut out)?;
+    }
+    let connection = Connection::open(&temp.0)?;
+    read_collection(&connection, name, today).context("Błąd podczas odczytu kolekcji Anki.")
+}
+
+fn read_collection(connection: &Connection, name: &str, today: NaiveDate) -> Result<Imported> {
+    let (crt, models, decks): (i64, String, String) =
+        connection.query_row("SELECT crt, models, decks FROM col", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    let collection_start = local_datetime(crt * 1000).date();
+    let models = parse_models(&serde_json::from_str(&models)?);
+    let deck_names: HashMap<i64, String> = serde_json::from_str::<Value>(&decks)?
+        .as_object()
+        .map(|decks| {
+            decks
+                .values()
+                .filter_map(|deck| Some((deck["id"].as_i64()?, deck["name"].as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut statement = connection.prepare(
+        "SELECT c.id, c.did, c.ord, c.type, c.queue, c.due, c.ivl, c.factor, c.reps, c.lapses,
+                c.data, n.mid, n.flds, n.tags, c.nid
+         FROM cards c JOIN notes n ON n.id = c.nid
+         ORDER BY c.did, c.type = 0, c.due, c.id",
+    )?;
+    let mut rows = statement.query([])?;
+    let mut decks: Vec<(i64, Deck)> = vec![];
+    let mut card_ids: HashMap<i64, Uuid> = HashMap::new();
+    let mut notes: HashMap<i64, Vec<(i64, Uuid)>> = HashMap::new();
+    let mut report = ImportReport::default();
+    let mut position = 0;
+    while let Some(row) = rows.next()? {
+        position += 1;
+        let anki_id: i64 = row.get(0)?;
+        let deck_id: i64 = row.get(1)?;
+        let ord: i64 = row.get(2)?;
+        let card_type: i64 = row.get(3)?;
+        let due: i64 = row.get(5)?;
+        let ivl: i64 = row.get(6)?;
+        let factor: i64 = row.get(7)?;
+        let lapses: i64 = row.get(9)?;
+        let data: String = row.get(10)?;
+        let model_id: i64 = row.get(11)?;
+        let fields: String = row.get(12)?;
+        let tags: String = row.get(13)?;
+        let note_id: i64 = row.get(14)?;
+
+        let Some(model) = models.get(&model_id) else {
+            report.skip(position, format!("Nieznany typ notatki: {}", model_id));
+            continue;
+        };
+        let card = if model.kind == MODEL_TYPE_CLOZE {
+            let (text, extra) = cloze_fields(model, &fields);
+            FlashCard::new_cloze(text, extra, ord as u32 + 1, today)
+        } else {
+            let (question, answer) = render_card(model, &fields, ord);
+            FlashCard::new(question, answer, today)
+        };
+        let mut card = match card {
+            Ok(card) => card,
+            Err(error) => {
+                report.skip(position, error);
+                continue;
+            }
+        };
+        if card_type == CARD_TYPE_REVIEW && ivl > 0 {
+            card.interval = ivl as u32;
+            card.next_review_date = collection_start + Duration::days(due);
+            card.last_review_date = card.next_review_date - Duration::days(ivl);
+            card.repetitions = 2;
+        } else if card_type != CARD_TYPE_NEW {
+            card.interval = 1;
+            card.repetitions = 0;
+            if due > 1_000_000_000 {
+                card.next_review_date = local_datetime(due * 1000).date().min(today);
+            }
+        }
+        if factor > 0 {
+            card.ef = factor as f32 / 1000.0;
+        }
+        card.lapses = lapses as u32;
+        card.set_tags(tags.split_whitespace());
+        if let Ok(memory) = serde_json::from_str::<Value>(&data)
+            && let (Some(s), Some(d)) = (memory["s"].as_f64(), memory["d"].as_f64())
+        {
+            card.stability = s as f32;
+            card.difficulty = d as f32;
+        }
+        card_ids.insert(anki_id, card.get_id());
+        notes.entry(note_id).or_default().push((ord, card.get_id()));
+
+        let deck = match decks.iter_mut().find(|(id, _)| *id == deck_id) {
+            Some((_, deck)) => deck,
+            None => {
+                let deck_name = deck_names
+                    .get(&deck_id)
+                    .cloned()
+                    .unwrap_or_else(|| "Anki".to_string());
+                decks.push((deck_id, Deck::new(deck_name)));
+                &mut decks.last_mut().unwrap().1
+            }
+        };
+        deck.add_card(card);
+        report.added += 1;
+    }
+
+    let review_log = read_review_log(connection, &card_ids)?;
+    let reviews = group_by_card(&review_log);
+    for (_, deck) in decks.iter_mut() {
+        update_repetitions(deck, &reviews);
+        link_note_cards(deck, &notes);
+    }
+    let mut decks: Vec<Deck> = decks.into_iter().map(|(_, deck)| deck).collect();
+    if decks.len() == 1 && !name.trim().is_empty() {
+        decks[0].set_name(name.trim().to_string());
+    }
+    Ok(Imported {
+        decks,
+        review_log,
+        report,
+    })
+}
+
+fn read_review_log(
+    connection: &Connection,
+    card_ids: &HashMap<i64, Uuid>,
+) -> Result<Vec<ReviewLogEntry>> {
+    let mut statement = connection
+        .prepare("SELECT id, cid, ease, ivl, lastIvl, factor, time FROM revlog ORDER BY id")?;
+    let mut rows = statement.query([])?;
+    let mut entries = vec![];
+    let mut previous_ease: HashMap<i64, f32> = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let cid: i64 = row.get(1)?;
+        let Some(card_id) = card_ids.get(&cid) else {
+            continue;
+        };
+        let ease: i64 = row.get(2)?;
+        let factor: i64 = row.get(5)?;
+        let new_ease = if factor > 0 {
+            factor as f32 / 1000.0
+        } else {
+            2.5
+        };
+        let ivl: i64 = row.get(3)?;
+        let last_ivl: i64 = row.get(4)?;
+        let time: i64 = row.get(6)?;
+        entries.push(ReviewLogEntry {
+            id: Uuid::new_v4(),
+            card_id: *card_id,
+            timestamp: local_datetime(row.get(0)?),
+            rating: match ease {
+                1 => 1,
+                2 => 3,
+                3 => 4,
+                _ => 5,
+            },
+            previous_interval: last_ivl.max(0) as u32,
+            new_interval: ivl.max(0) as u32,
+            previous_ease: previous_ease.insert(cid, new_ease).unwrap_or(new_ease),
+            new_ease,
+            time_spent_ms: time.max(0) as u64,
+            undoes: None,
+        });
+    }
+    Ok(entries)
+}
+
+fn group_by_card(review_log: &[ReviewLogEntry]) -> HashMap<Uuid, Vec<&ReviewLogEntry>> {
+    let mut reviews: HashMap<Uuid, Vec<&ReviewLogEntry>> = HashMap::new();
+    for entry in review_log {
+        reviews.entry(entry.card_id).or_default().push(entry);
+    }
+    reviews
+}
+
+fn update_repetitions(deck: &mut Deck, reviews: &HashMap<Uuid, Vec<&ReviewLogEntry>>) {
+    for card in deck.cards.iter_mut().filter(|card| card.repetitions > 0) {
+        let Some(entries) = reviews.get(&card.get_id()) else {
+            continue;
+        };
+        let streak = entries
+            .iter()
+            .rev()
+            .take_while(|entry| entry.rating >= 3)
+            .count();
+        if streak > 0 {
+            card.repetitions = streak as u32;
+        }
+    }
+}
+
+fn link_note_cards(deck: &mut Deck, notes: &HashMap<i64, Vec<(i64, Uuid)>>) {
+    for cards in notes.values().filter(|cards| cards.len() > 1) {
+        let indices: Vec<usize> = cards
+            .iter()
+            .filter_map(|(_, id)| deck.get_card_index(*id))
+            .collect();
+        if indices.iter().all(|&i| deck.cards[i].get_cloze().is_some()) {
+            let note_id = Some(Uuid::new_v4());
+            for i in indices {
+                deck.cards[i].note_id = note_id;
+            }
+            continue;
+        }
+        if cards.len() != 2 {
+            continue;
+        }
+        let (forward, reverse) = if cards[0].0 < cards[1].0 {
+            (cards[0].1, cards[1].1)
+        } else {
+            (cards[1].1, cards[0].1)
+        };
+        let (Some(first), Some(second)) =
+            (deck.get_card_index(forward), deck.get_card_index(reverse))
+        else {
+            continue;
+        };
+        let (a, b) = (&deck.cards[first], &deck.cards[second]);
+        if a.question != b.answer || a.answer != b.question {
+            continue;
+        }
+        let note_id = Some(Uuid::new_v4());
+        deck.cards[first].note_id = note_id;
+        deck.cards[second].note_id = note_id;
+        deck.cards[second].reversed = true;
+    }
+}
+
+fn parse_models(models: &Value) -> HashMap<i64, Model> {
+    let Some(models) = models.as_object() else {
+        return HashMap::new();
+    };
+    models
+        .values()
+        .filter_map(|model| {
+            let id = model["id"]
+                .as_i64()
+                .or_else(|| model["id"].as_str()?.parse().ok())?;
+            let fields = model["flds"]
+                .as_array()?
+                .iter()
+                .filter_map(|f| Some(f["name"].as_str()?.to_string()))
+                .collect();
+            let templates = model["tmpls"]
+                .as_array()?
+                .iter()
+                .filter_map(|t| {
+                    Some((
+                        t["qfmt"].as_str()?.to_string(),
+                        t["afmt"].as_str()?.to_string(),
+                    ))
+                })
+                .collect();
+            Some((
+                id,
+                Model {
+                    kind: model["type"].as_i64().unwrap_or(0),
+                    fields,
+                    templates,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn render_card(model: &Model, fields: &str, ord: i64) -> (String, String) {
+    let values: HashMap<&str, &str> = model
+        .fields
+        .iter()
+        .map(String::as_str)
+        .zip(fields.split(FIELD_SEPARATOR))
+        .collect();
+    let Some((qfmt, afmt)) = model.templates.get(ord as usize) else {
+        let mut parts = fields.split(FIELD_SEPARATOR);
+        let question = strip_html(parts.next().unwrap_or_default());
+        let answer = strip_html(&parts.collect::<Vec<_>>().join("\n"));
+        return (question, answer);
+    };
+    let question = render_template(qfmt, &values, "");
+    let answer = render_template(afmt, &values, &question);
+    let answer = match answer.find("<hr id=answer>") {
+        Some(idx) => answer[idx + "<hr id=answer>".len()..].to_string(),
+        None => answer
+            .strip_prefix(question.as_str())
+            .unwrap_or(&answer)
+            .to_string(),
+    };
+    (strip_html(&question), strip_html(&answer))
+}
+
+fn render_template(template: &str, values: &HashMap<&str, &str>, front_side: &str) -> String {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 2..start + end].trim();
+        rest = &rest[start + end + 2..];
+        if let Some(section) = tag.strip_prefix('#').or_else(|| tag.strip_prefix('^')) {
+            let closing = format!("{{{{/{}}}}}", section);
+            let (inner, after) = match rest.find(&closing) {
+                Some(idx) => (&rest[..idx], &rest[idx + closing.len()..]),
+                None => (rest, ""),
+            };
+            let filled = values
+                .get(section)
+                .is_some_and(|value| !strip_html(value).trim().is_empty());
+            if filled == tag.starts_with('#') {
+                output.push_str(&render_template(inner, values, front_side));
+            }
+            rest = after;
+            continue;
+        }
+        if tag == "FrontSide" {
+            output.push_str(front_side);
+            continue;
+        }
+        let (filter, field) = match tag.rsplit_once(':') {
+            Some((filter, field)) => (filter, field),
+            None => ("", tag),
+        };
+        let value = values.get(field).copied().unwrap_or_default();
+        match filter {
+            "type" => {}
+            "cloze" => output.extend(
+                cloze_segments(value, None, true)
+                    .into_iter()
+                    .map(|(text, _)| text),
+            ),
+            "text" => output.push_str(&strip_html(value)),
+            _ => output.push_str(value),
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn cloze_fields(model: &Model, fields: &str) -> (String, String) {
+    let values: Vec<&str> = fields.split(FIELD_SEPARATOR).collect();
+    let text_field = model
+        .templates
+        .first()
+        .and_then(|(qfmt, _)| qfmt.split("{{cloze:").nth(1)?.split("}}").next())
+        .and_then(|name| model.fields.iter().position(|field| field == name.trim()))
+        .unwrap_or(0);
+    let text = strip_html(values.get(text_field).copied().unwrap_or_default());
+    let extra = values
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != text_field)
+        .map(|(_, value)| strip_html(value))
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (text, extra)
+}
+
+fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_lowercase();
+        if tag.starts_with("br") || tag.starts_with("/div") || tag.starts_with("/p") {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+fn tags_field(tags: &[String]) -> String {
+    if tags.is_empty() {
+        String::new()
+    } else {
+        let tags: Vec<String> = tags.iter().map(|tag| normalize_tag(tag)).collect();
+        format!(" {} ", tags.join(" "))
+    }
+}
+
+fn local_datetime(millis: i64) -> NaiveDateTime {
+    DateTime::from_timestamp_millis(millis)
+        .map(|utc| utc.with_timezone(&Local).naive_local())
+        .unwrap_or_default()
+}
+
+fn field_checksum(text: &str) -> i64 {
+    let digest = Sha1::digest(strip_html(text).as_bytes());
+    i64::from_str_radix(&format!("{:x}", digest)[..8], 16).unwrap_or(0)
+}
+
+pub fn export(decks: &[&Deck], review_log: &[ReviewLogEntry], path: &Path) -> Result<()> {
+    let temp = TempFile::new();
+    let connection = Connection::open(&temp.0)?;
+    write_collection(&connection, decks, review_log)?;
+    drop(connection);
+
+    let mut database = vec![];
+    File::open(&temp.0)?.read_to_end(&mut database)?;
+    let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+    let options = SimpleFileOptions::default();
+    zip.start_file("collection.anki2", options)?;
+    zip.write_all(&database)?;
+    zip.start_file("media", options)?;
+    zip.write_all(b"{}")?;
+    write_atomic(path, &zip.finish()?.into_inner())
+}
+
+fn write_collection(
+    connection: &Connection,
+    decks: &[&Deck],
+    review_log: &[ReviewLogEntry],
+) -> Result<()> {
+    connection.execute_batch(SCHEMA)?;
+    let now = Local::now();
+    let now_ms = now.timestamp_millis();
+    let today = now.date_naive();
+    let start = decks
+        .iter()
+        .flat_map(|deck| deck.cards.iter())
+        .map(|card| card.last_review_date)
+        .min()
+        .unwrap_or(today)
+        .min(today);
+    let crt = Local
+        .from_local_datetime(&start.and_hms_opt(4, 0, 0).unwrap_or_default())
+        .earliest()
+        .ok_or_else(|| anyhow!("Nieprawidłowa data utworzenia kolekcji."))?
+        .timestamp();
+    let model_id = now_ms;
+    let reversed_model_id = now_ms + 1;
+    let cloze_model_id = now_ms + 2;
+    let deck_ids: Vec<i64> = (1..=decks.len() as i64).map(|i| now_ms + i).collect();
+
+    let model_entry = |id: i64, name: &str, kind: i64, fields: [&str; 2], templates: Value| {
+        let req = match templates.as_array() {
+            Some(templates) if kind == 0 => (0..templates.len())
+                .map(|ord| json!([ord, "any", [ord]]))
+                .collect(),
+            _ => vec![json!([0, "any", [0]])],
+        };
+        json!({
+            "id": id, "name": name, "type": kind, "mod": now.timestamp(), "usn": -1,
+            "sortf": 0, "did": deck_ids.first().copied().unwrap_or(1), "tags": [], "vers": [],
+            "tmpls": templates,
+            "flds": [
+                {"name": fields[0], "ord": 0, "sticky": false, "rtl": false, "font": "Arial", "size": 20, "media": []},
+                {"name": fields[1], "ord": 1, "sticky": false, "rtl": false, "font": "Arial", "size": 20, "media": []}
+            ],
+            "css": ".card { font-family: arial; font-size: 20px; text-align: center; color: black; background-color: white; }",
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "req": req
+        })
+    };
+    let forward_template = json!({
+        "name": "Card 1", "ord": 0, "qfmt": "{{Front}}",
+        "afmt": "{{FrontSide}}\n\n<hr id=answer>\n\n{{Back}}",
+        "did": null, "bqfmt": "", "bafmt": ""
+    });
+    let reverse_template = json!({
+        "name": "Card 2", "ord": 1, "qfmt": "{{Back}}",
+        "afmt": "{{FrontSide}}\n\n<hr id=answer>\n\n{{Front}}",
+        "did": null, "bqfmt": "", "bafmt": ""
+    });
+    let cloze_template = json!({
+        "name": "Cloze", "ord": 0, "qfmt": "{{cloze:Text}}",
+        "afmt": "{{cloze:Text}}<br>\n{{Back Extra}}",
+        "did": null, "bqfmt": "", "bafmt": ""
+    });
+    let basic_fields = ["Front", "Back"];
+    let model = json!({
+        model_id.to_string(): model_entry(
+            model_id,
+            "Basic",
+            0,
+            basic_fields,
+            json!([forward_template]),
+        ),
+        reversed_model_id.to_string(): model_entry(
+            reversed_model_id,
+            "Basic (and reversed card)",
+            0,
+            basic_fields,
+            json!([forward_template, reverse_template]),
+        ),
+        cloze_model_id.to_string(): model_entry(
+            cloze_model_id,
+            "Cloze",
+            MODEL_TYPE_CLOZE,
+            ["Text", "Back Extra"],
+            json!([cloze_template]),
+        ),
+    });
+    let deck_entry = |id: i64, name: &str| {
+        json!({
+            "id": id, "name": name, "mod": now.timestamp(), "usn": -1, "desc": "", "dyn": 0,
+            "conf": 1, "collapsed": false, "extendNew": 10, "extendRev": 50,
+            "newToday": [0, 0], "revToday": [0, 0], "lrnToday": [0, 0], "timeToday": [0, 0]
+        })
+    };
+    let mut deck_entries = serde_json::Map::new();
+    deck_entries.insert("1".to_string(), deck_entry(1, "Default"));
+    for (deck, id) in decks.iter().zip(&deck_ids) {
+        deck_entries.insert(id.to_string(), deck_entry(*id, &deck.get_name()));
+    }
+    let dconf = json!({
+        "1": {
+            "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60, "autoplay": true,
+            "timer": 0, "replayq": true, "dyn": false,
+            "new": {"bury": true, "delays": [1.0, 10.0], "initialFactor": 2500,
+                    "ints": [1, 4, 7], "order": 1, "perDay": 20, "separate": true},
+            "lapse": {"delays": [10.0], "leechAction": 0, "leechFails": 8, "minInt": 1, "mult": 0.0},
+            "rev": {"bury": true, "ease4": 1.3, "fuzz": 0.05, "ivlFct": 1.0, "maxIvl": 36500,
+                    "minSpace": 1, "perDay": 200}
+        }
+    });
+    let card_count: usize = decks.iter().map(|deck| deck.get_card_count()).sum();
+    let conf = json!({
+        "nextPos": card_count + 1, "estTimes": true, "activeDecks": [1],
+        "sortType": "noteFld", "timeLim": 0, "sortBackwards": false, "addToCur": true,
+        "curDeck": 1, "newBury": true, "newSpread": 0, "dueCounts": true,
+        "curModel": model_id.to_string(), "collapseTime": 1200
+    });
+    connection.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?3, 11, 0, 0, 0, ?4, ?5, ?6, ?7, '{}')",
+        params![
+            crt,
+            now_ms,
+            now_ms,
+            conf.to_string(),
+            model.to_string(),
+            Value::Object(deck_entries).to_string(),
+            dconf.to_string()
+        ],
+    )?;
+
+    let reviews = group_by_card(review_log);
+    let mut anki_ids: HashMap<Uuid, i64> = HashMap::new();
+    let mut position = 0;
+    for (deck, deck_id) in decks.iter().zip(&deck_ids) {
+        let notes = deck.get_note_groups();
+        for (index, card) in deck.cards.iter().enumerate() {
+            let note = &notes[&card.get_note_id()];
+            let both = deck.direction_of(note) == CardDirection::Both;
+            let siblings: Vec<usize> = note.iter().copied().filter(|&i| i != index).collect();
+            let cloze = card.get_cloze().is_some();
+            if both && card.is_reversed() || cloze && siblings.iter().any(|&i| i < index) {
+                continue;
+            }
+            let mut note_cards = vec![card];
+            if both || cloze {
+                note_cards.extend(
+                    siblings
+                        .into_iter()
+                        .map(|i| &deck.cards[i])
+                        .filter(|sibling| {
+                            sibling.is_reversed() != card.is_reversed()
+                                || sibling.get_cloze().is_some()
+                        }),
+                );
+            }
+            let (front, back) = if both {
+                card.get_note_text()
+            } else {
+                (card.get_question(), card.get_answer())
+            };
+            let note_model_id = match (both, cloze) {
+                (true, _) => reversed_model_id,
+                (_, true) => cloze_model_id,
+                _ => model_id,
+            };
+            let note_id = now_ms + position;
+            connection.execute(
+                "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ?5, ?6, ?7, ?8, 0, '')",
+                params![
+                    note_id,
+                    card.get_id().simple().to_string()[..10].to_string(),
+                    note_model_id,
+                    now.timestamp(),
+                    tags_field(card.get_tags()),
+                    format!(
+                        "{}{}{}",
+                        escape_html(&front),
+                        FIELD_SEPARATOR,
+                        escape_html(&back)
+                    ),
+                    front,
+                    field_checksum(&front),
+                ],
+            )?;
+            for card in note_cards {
+                let id = now_ms + position;
+                position += 1;
+                anki_ids.insert(card.get_id(), id);
+                let (card_type, due, ivl) = if card.is_new() {
+                    (CARD_TYPE_NEW, position, 0)
+                } else {
+                    (
+                        CARD_TYPE_REVIEW,
+                        (card.next_review_date - start).num_days(),
+                        card.interval.max(1) as i64,
+                    )
+                };
+                let reps = reviews
+                    .get(&card.get_id())
+                    .map_or(0, Vec::len)
+                    .max(card.repetitions as usize);
+                connection.execute(
+                    "INSERT INTO cards VALUES (?1, ?2, ?3, ?4, ?5, -1, ?6, ?6, ?7, ?8, ?9, ?10, ?11, 0, 0, 0, 0, '')",
+                    params![
+                        id,
+                        note_id,
+                        deck_id,
+                        card.get_cloze()
+                            .map_or(card.is_reversed() as i64, |n| n.saturating_sub(1) as i64),
+                        now.timestamp(),
+                        card_type,
+                        due,
+                        ivl,
+                        (card.ef * 1000.0).round() as i64,
+                        reps,
+                        card.lapses
+                    ],
+                )?;
+            }
+        }
+    }
+
+    let mut last_id = 0;
+    for entry in review_log {
+        let Some(cid) = anki_ids.get(&entry.card_id) else {
+            continue;
+        };
+        let timestamp = Local
+            .from_local_datetime(&entry.timestamp)
+            .earliest()
+            .map(|t| t.timestamp_millis())
+            .unwrap_or(now_ms);
+        let id = timestamp.max(last_id + 1);
+        last_id = id;
+        connection.execute(
+            "INSERT INTO revlog VALUES (?1, ?2, -1, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                id,
+                cid,
+                match entry.rating {
+                    0..=2 => 1,
+                    3 => 2,
+                    4 => 3,
+                    _ => 4,
+                },
+                entry.new_interval,
+                entry.previous_interval,
+                (entry.new_ease * 1000.0).round() as i64,
+                entry.time_spent_ms.min(60_000),
+                if entry.previous_interval == 0 { 0 } else { 1 },
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Collection;
+    use crate::model::testing::{TempDir, card, deck, now, today};
+
+    #[test]
+    fn package_round_trips_cards_and_reviews() {
+        let mut collection = Collection::default();
+        let mut deck = deck(&[("dom", "house"), ("kot", "cat")]);
+        deck.set_learning_steps(" / ".parse().unwrap());
+        let mut tagged = card("a < b & c", "pierwsza\ndruga");
+        tagged.set_tags(["gramatyka", "b1"]);
+        deck.add_card(tagged);
+        collection.add_deck(deck);
+        for rating in [4, 4] {
+            collection.review_card(0, 0, rating, now(), std::time::Duration::from_secs(2));
+        }
+        let dir = TempDir::new();
+        let path = dir.path().join("talia.apkg");
+        export(&[&collection.decks[0]], collection.get_review_log(), &path).unwrap();
+
+        let imported = import(&path, "", today()).unwrap();
+        assert_eq!(imported.report.added, 3);
+        assert_eq!(imported.decks.len(), 1);
+        let original = &collection.decks[0];
+        let loaded = &imported.decks[0];
+        assert_eq!(loaded.get_name(), original.get_name());
+        for (loaded, original) in loaded.cards.iter().zip(&original.cards) {
+            assert_eq!(loaded.get_question(), original.get_question());
+            assert_eq!(loaded.get_answer(), original.get_answer());
+            assert_eq!(loaded.get_tags(), original.get_tags());
+            assert_eq!(loaded.interval, original.interval);
+            assert_eq!(loaded.repetitions, original.repetitions);
+            assert_eq!(loaded.next_review_date, original.next_review_date);
+            assert!((loaded.ef - original.ef).abs() < 1e-3);
+            assert_eq!(loaded.is_new(), original.is_new());
+        }
+        let ratings: Vec<u32> = imported.review_log.iter().map(|e| e.rating).collect();
+        assert_eq!(ratings, vec![4, 4]);
+        assert!(
+            imported
+                .review_log
+                .iter()
+                .all(|entry| entry.card_id == loaded.cards[0].get_id())
+        );
+    }
+
+    #[test]
+    fn package_keeps_decks_apart() {
+        let mut first = deck(&[("dom", "house")]);
+        first.set_name("Pierwsza".to_string());
+        let mut second = deck(&[("kot", "cat"), ("pies", "dog")]);
+        second.set_name("Druga".to_string());
+        let dir = TempDir::new();
+        let path = dir.path().join("kolekcja.apkg");
+        export(&[&first, &second], &[], &path).unwrap();
+
+        let imported = import(&path, "", today()).unwrap();
+        let decks: Vec<(String, usize)> = imported
+            .decks
+            .iter()
+            .map(|deck| (deck.get_name(), deck.get_card_count()))
+            .collect();
+        assert_eq!(decks, vec![("Pierwsza".into(), 1), ("Druga".into(), 2)]);
+    }
+
+    #[test]
+    fn package_keeps_notes_together() {
+        let mut deck = deck(&[("dom", "house")]);
+        for (text, extra, direction) in [
+            ("pies", "dog", CardDirection::Both),
+            ("{{c1::Ala}} ma {{c2::kota}}", "", CardDirection::Forward),
+        ] {
+            for card in FlashCard::new_note(text.into(), extra.into(), direction, today()).unwrap()
+            {
+                deck.add_card(card);
+            }
+        }
+        let dir = TempDir::new();
+        let path = dir.path().join("talia.apkg");
+        export(&[&deck], &[], &path).unwrap();
+
+        let imported = import(&path, "", today()).unwrap();
+        let loaded = &imported.decks[0];
+        assert_eq!(loaded.get_card_count(), 5);
+        let notes = loaded.get_note_groups();
+        let mut sizes: Vec<usize> = notes.values().map(Vec::len).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 2, 2]);
+        let pies = (0..5)
+            .find(|&i| loaded.cards[i].get_note_text().0 == "pies")
+            .unwrap();
+        assert_eq!(loaded.get_note_direction(pies), CardDirection::Both);
+    }
+
+    #[test]
+    fn renders_templates_and_cloze() {
+        assert_eq!(
+            strip_html("<b>a</b>&nbsp;&amp;<br><div>b</div>c"),
+            "a &\nb\nc"
+        );
+        let model = Model {
+            kind: 0,
+            fields: vec!["Front".into(), "Back".into(), "Notes".into()],
+            templates: vec![(
+                "{{Front}}{{#Notes}} ({{Notes}}){{/Notes}}".into(),
+                "{{FrontSide}}<hr id=answer>{{Back}}".into(),
+            )],
+        };
+        let fields = format!("dom{0}house{0}", FIELD_SEPARATOR);
+        assert_eq!(
+            render_card(&model, &fields, 0),
+            ("dom".into(), "house".into())
+        );
+        let fields = format!("dom{0}house{0}rzeczownik", FIELD_SEPARATOR);
+        assert_eq!(render_card(&model, &fields, 0).0, "dom (rzeczownik)");
+
+        let cloze = Model {
+            kind: 1,
+            fields: vec!["Extra".into(), "Text".into()],
+            templates: vec![("{{cloze:Text}}".into(), "{{cloze:Text}}".into())],
+        };
+        let fields = format!("<i>zwierzę</i>{0}Ala ma {{{{c1::kota}}}}", FIELD_SEPARATOR);
+        assert_eq!(
+            cloze_fields(&cloze, &fields),
+            ("Ala ma {{c1::kota}}".into(), "zwierzę".into())
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use super::Imported;
+use crate::model::{CardDirection, Deck, FlashCard, ImportReport, is_cloze};
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+const DELIMITER_CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+const QUESTION_HEADERS: [&str; 5] = ["question", "pytanie", "front", "przód", "przod"];
+const ANSWER_HEADERS: [&str; 6] = ["answer", "odpowiedź", "odpowiedz", "back", "tył", "tyl"];
+const TAGS_HEADERS: [&str; 3] = ["tags", "tagi", "tag"];
+const DIRECTION_HEADERS: [&str; 4] = ["direction", "kierunek", "cards", "karty"];
+
+#[derive(Debug, Error)]
+pub enum CsvError {
+    #[error("Nieznana opcja importu: \"{0}\".")]
+    UnknownOption(String),
+    #[error("Nieprawidłowa wartość opcji \"{0}\": \"{1}\".")]
+    InvalidValue(String, String),
+    #[error("Numery kolumn zaczynają się od 1.")]
+    ZeroColumn,
+    #[error("Plik nie ma nagłówka, więc kolumnę \"{0}\" trzeba podać numerem.")]
+    NamedColumnWithoutHeader(String),
+    #[error("W nagłówku nie ma kolumny \"{0}\".")]
+    UnknownColumn(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Index(index) => write!(f, "{}", index + 1),
+            Column::Name(name) => f.write_str(name),
+        }
+    }
+}
+impl FromStr for Column {
+    type Err = CsvError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().parse::<usize>() {
+            Ok(0) => Err(CsvError::ZeroColumn),
+            Ok(number) => Ok(Column::Index(number - 1)),
+            Err(_) => Ok(Column::Name(s.trim().to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: Option<u8>,
+    pub quoting: bool,
+    pub header: Option<bool>,
+    pub question: Option<Column>,
+    pub answer: Option<Column>,
+    pub tags: Option<Column>,
+    pub direction: CardDirection,
+}
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: None,
+            quoting: true,
+            header: None,
+            question: None,
+            answer: None,
+            tags: None,
+            direction: CardDirection::Forward,
+        }
+    }
+}
+impl CsvOptions {
+    pub fn parse(spec: &str) -> Result<CsvOptions, CsvError> {
+        let mut options = CsvOptions::default();
+        for option in spec.split_whitespace() {
+            let Some((key, value)) = option.split_once('=') else {
+                return Err(CsvError::UnknownOption(option.to_string()));
+            };
+            let invalid = || CsvError::InvalidValue(key.to_string(), value.to_string());
+            match key.to_lowercase().as_str() {
+                "sep" | "separator" | "delimiter" => {
+                    options.delimiter = Some(parse_delimiter(value).ok_or_else(invalid)?)
+                }
+                "quote" | "quoting" | "cudzysłów" => {
+                    options.quoting = parse_flag(value).ok_or_else(invalid)?
+                }
+                "header" | "nagłówek" | "naglowek" => {
+                    options.header = match value {
+                        "auto" => None,
+                        _ => Some(parse_flag(value).ok_or_else(invalid)?),
+                    }
+                }
+                "question" | "pytanie" => options.question = Some(value.parse()?),
+                "answer" | "odpowiedź" | "odpowiedz" => options.answer = Some(value.parse()?),
+                "tags" | "tagi" => options.tags = Some(value.parse()?),
+                "cards" | "karty" | "direction" | "kierunek" => {
+                    options.direction = value.parse().map_err(|_| invalid())?
+                }
+                _ => return Err(CsvError::UnknownOption(key.to_string())),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn to_spec(&self) -> String {
+        let mut spec = vec![];
+        if let Some(delimiter) = self.delimiter {
+            spec.push(match delimiter {
+                b'\t' => "sep=tab".to_string(),
+                b' ' => "sep=space".to_string(),
+                _ => format!("sep={}", delimiter as char),
+            });
+        }
+        if !self.quoting {
+            spec.push("quote=nie".to_string());
+        }
+        if let Some(header) = self.header {
+            spec.push(format!("header={}", if header { "tak" } else { "nie" }));
+        }
+        for (key, column) in [
+            ("question", &self.question),
+            ("answer", &self.answer),
+            ("tags", &self.tags),
+        ] {
+            if let Some(column) = column {
+                spec.push(format!("{}={}", key, column));
+            }
+        }
+        if self.direction != CardDirection::Forward {
+            spec.push(format!("cards={}", self.direction));
+        }
+        spec.join(" ")
+    }
+}
+
+pub fn parse_delimiter(value: &str) -> Option<u8> {
+    match value {
+        "tab" | "\\t" | "\t" => Some(b'\t'),
+        "space" | "spacja" => Some(b' '),
+        _ if value.len() == 1 && value.is_ascii() => Some(value.as_bytes()[0]),
+        _ => None,
+    }
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "tak" | "yes" | "y" | "t" | "true" | "1" => Some(true),
+        "nie" | "no" | "n" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn is_delimited_text(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()),
+        Some(ext) if ext == "csv" || ext == "tsv" || ext == "tab" || ext == "txt"
+    )
+}
+
+fn detect_delimiter(path: &Path, contents: &str) -> u8 {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    if matches!(extension.as_deref(), Some("tsv" | "tab")) {
+        return b'\t';
+    }
+    let first_line = contents
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    DELIMITER_CANDIDATES
+        .into_iter()
+        .max_by_key(|&candidate| first_line.bytes().filter(|&b| b == candidate).count())
+        .filter(|&candidate| first_line.as_bytes().contains(&candidate))
+        .unwrap_or(b',')
+}
+
+fn find_header(header: &StringRecord, names: &[&str]) -> Option<usize> {
+    header
+        .iter()
+        .position(|cell| names.contains(&cell.trim().to_lowercase().as_str()))
+}
+
+fn resolve_column(
+    column: &Option<Column>,
+    header: Option<&StringRecord>,
+    names: &[&str],
+    fallback: Option<usize>,
+) -> Result<Option<usize>, CsvError> {
+    match (column, header) {
+        (Some(Column::Index(index)), _) => Ok(Some(*index)),
+        (Some(Column::Name(name)), Some(header)) => header
+            .iter()
+            .position(|cell| cell.trim().eq_ignore_ascii_case(name))
+            .map(Some)
+            .ok_or_else(|| CsvError::UnknownColumn(name.clone())),
+        (Some(Column::Name(name)), None) => Err(CsvError::NamedColumnWithoutHeader(name.clone())),
+        (None, Some(header)) => Ok(find_header(header, names).or(fallback)),
+        (None, None) => Ok(fallback),
+    }
+}
+
+pub fn import(path: &Path, name: &str, options: &CsvOptions, today: NaiveDate) -> Result<Imported> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Nie udało się otworzyć pliku: {}", path.display()))?;
+    let contents = contents.trim_start_matches('\u{feff}');
+    let mut reader = ReaderBuilder::new()
+        .delimiter(
+            options
+                .delimiter
+                .unwrap_or_else(|| detect_delimiter(path, contents)),
+        )
+        .quoting(options.quoting)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let mut records = reader.records().peekable();
+
+    let first = match records.peek() {
+        Some(Ok(record)) => Some(record.clone()),
+        _ => None,
+    };
+    let has_header = options.header.unwrap_or_else(|| {
+        first.as_ref().is_some_and(|record| {
+            [&QUESTION_HEADERS[..], &ANSWER_HEADERS, &TAGS_HEADERS]
+                .iter()
+                .any(|names| find_header(record, names).is_some())
+        })
+    });
+    let header = if has_header {
+        records.next();
+        first.as_ref()
+    } else {
+        None
+    };
+    let question = resolve_column(&options.question, header, &QUESTION_HEADERS, Some(0))?;
+    let answer = resolve_column(&options.answer, header, &ANSWER_HEADERS, Some(1))?;
+    let tags = resolve_column(&options.tags, header, &TAGS_HEADERS, None)?;
+    let direction = resolve_column(&None, header, &DIRECTION_HEADERS, None)?;
+
+    let mut deck = Deck::new(name.to_string());
+    let mut report = ImportReport::default();
+    for record in records {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                let line = error.position().map_or(0, |p| p.line() as usize);
+                report.skip(line, error);
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let cell = |column: Option<usize>| column.and_then(|c| record.get(c));
+        let (Some(q), Some(a)) = (
+            cell(question),
+            cell(answer).or_else(|| cell(question).filter(|q| is_cloze(q)).map(|_| "")),
+        ) else {
+            report.skip(line, format!("Za mało kolumn w wierszu: {}.", record.len()));
+            continue;
+        };
+        let note_direction = match cell(direction).map(str::trim).filter(|d| !d.is_empty()) {
+            Some(text) => match text.parse() {
+                Ok(direction) => direction,
+                Err(error) => {
+                    report.skip(line, error);
+                    continue;
+                }
+            },
+            None => options.direction,
+        };
+        match FlashCard::new_note(
+            q.trim().to_string(),
+            a.trim().to_string(),
+            note_direction,
+            today,
+        ) {
+            Ok(cards) => {
+                report.added += cards.len();
+                for mut card in cards {
+                    if let Some(tags) = cell(tags) {
+                        card.set_tags(tags.split([' ', ',', ';']));
+                    }
+                    deck.add_card(card);
+                }
+            }
+            Err(error) => report.skip(line, error),
+        }
+    }
+    Ok(Imported {
+        decks: vec![deck],
+        review_log: vec![],
+        report,
+    })
+}
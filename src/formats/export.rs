@@ -0,0 +1,375 @@
+use super::anki;
+use crate::model::{CardDirection, Collection, Deck, ReviewLogEntry, cloze_segments};
+use crate::storage::write_atomic;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    SimpleJson,
+    FullJson,
+    Csv,
+    Markdown,
+    Anki,
+}
+impl ExportFormat {
+    pub fn next(self) -> ExportFormat {
+        match self {
+            ExportFormat::SimpleJson => ExportFormat::FullJson,
+            ExportFormat::FullJson => ExportFormat::Csv,
+            ExportFormat::Csv => ExportFormat::Markdown,
+            ExportFormat::Markdown => ExportFormat::Anki,
+            ExportFormat::Anki => ExportFormat::SimpleJson,
+        }
+    }
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::SimpleJson => "json",
+            ExportFormat::FullJson => "full.json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Anki => "apkg",
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::SimpleJson => "JSON (pytania i odpowiedzi)",
+            ExportFormat::FullJson => "JSON (pełny, z postępami)",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Anki => "Anki (.apkg)",
+        }
+    }
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        [
+            ExportFormat::FullJson,
+            ExportFormat::SimpleJson,
+            ExportFormat::Csv,
+            ExportFormat::Markdown,
+            ExportFormat::Anki,
+        ]
+        .into_iter()
+        .find(|format| name.ends_with(&format!(".{}", format.extension())))
+    }
+}
+impl FromStr for ExportFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::SimpleJson),
+            "full-json" | "full" => Ok(ExportFormat::FullJson),
+            "csv" => Ok(ExportFormat::Csv),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "apkg" | "anki" => Ok(ExportFormat::Anki),
+            _ => Err(format!(
+                "nieznany format: {} (dostępne: json, full-json, csv, markdown, apkg)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SimpleCard<'a> {
+    id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deck: Option<String>,
+    question: String,
+    answer: String,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<CardDirection>,
+}
+
+#[derive(Serialize)]
+struct FullExport<'a> {
+    decks: Vec<&'a Deck>,
+    review_log: Vec<&'a ReviewLogEntry>,
+}
+
+pub fn export(
+    collection: &Collection,
+    deck_index: Option<usize>,
+    format: ExportFormat,
+    path: &Path,
+) -> Result<usize> {
+    let decks: Vec<&Deck> = match deck_index {
+        Some(idx) => collection.decks.get(idx).into_iter().collect(),
+        None => collection.decks.iter().collect(),
+    };
+    let card_ids: HashSet<Uuid> = decks
+        .iter()
+        .flat_map(|deck| deck.cards.iter().map(|card| card.get_id()))
+        .collect();
+    let review_log: Vec<&ReviewLogEntry> = collection
+        .get_effective_reviews()
+        .filter(|entry| card_ids.contains(&entry.card_id))
+        .collect();
+    let with_deck_names = deck_index.is_none();
+
+    let (contents, count) = match format {
+        ExportFormat::Anki => {
+            let review_log: Vec<ReviewLogEntry> = review_log.into_iter().cloned().collect();
+            anki::export(&decks, &review_log, path)?;
+            return Ok(card_ids.len());
+        }
+        ExportFormat::SimpleJson => {
+            let cards: Vec<SimpleCard> = decks
+                .iter()
+                .flat_map(|deck| {
+                    deck.cards.iter().enumerate().filter_map(|(i, card)| {
+                        let direction = deck.get_note_direction(i);
+                        if is_repeated_note(deck, i, direction) {
+                            return None;
+                        }
+                        let (question, answer) = card.get_note_text();
+                        Some(SimpleCard {
+                            id: card.get_id(),
+                            deck: with_deck_names.then(|| deck.get_name()),
+                            question,
+                            answer,
+                            tags: card.get_tags(),
+                            direction: (direction != CardDirection::Forward).then_some(direction),
+                        })
+                    })
+                })
+                .collect();
+            (serde_json::to_string_pretty(&cards)?, cards.len())
+        }
+        ExportFormat::FullJson => (
+            serde_json::to_string_pretty(&FullExport { decks, review_log })?,
+            card_ids.len(),
+        ),
+        ExportFormat::Csv => to_csv(&decks, with_deck_names)?,
+        ExportFormat::Markdown => (to_markdown(&decks), note_count(&decks)),
+    };
+    write_atomic(path, contents.as_bytes())
+        .with_context(|| format!("Nie udało się zapisać pliku: {}", path.display()))?;
+    Ok(count)
+}
+
+fn note_count(decks: &[&Deck]) -> usize {
+    decks
+        .iter()
+        .map(|deck| {
+            (0..deck.cards.len())
+                .filter(|&i| !is_repeated_cloze(deck, i))
+                .count()
+        })
+        .sum()
+}
+
+fn to_csv(decks: &[&Deck], with_deck_names: bool) -> Result<(String, usize)> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let mut header = vec![
+        "question",
+        "answer",
+        "tags",
+        "direction",
+        "due",
+        "interval",
+        "ease",
+        "repetitions",
+        "lapses",
+    ];
+    if with_deck_names {
+        header.insert(0, "deck");
+    }
+    writer.write_record(&header)?;
+    let mut count = 0;
+    for deck in decks {
+        for (i, card) in deck.cards.iter().enumerate() {
+            let direction = deck.get_note_direction(i);
+            if is_repeated_note(deck, i, direction) {
+                continue;
+            }
+            let (question, answer) = card.get_note_text();
+            let mut record = vec![
+                question,
+                answer,
+                card.get_tags().join(" "),
+                direction.to_string(),
+                card.get_date().to_string(),
+                card.get_interval().to_string(),
+                format!("{:.2}", card.get_ef()),
+                card.get_repetitions().to_string(),
+                card.lapses.to_string(),
+            ];
+            if with_deck_names {
+                record.insert(0, deck.get_name());
+            }
+            writer.write_record(&record)?;
+            count += 1;
+        }
+    }
+    Ok((String::from_utf8(writer.into_inner()?)?, count))
+}
+
+fn to_markdown(decks: &[&Deck]) -> String {
+    let mut output = String::new();
+    for deck in decks {
+        output.push_str(&format!("# {}\n\n", deck.get_name()));
+        let notes = (0..deck.cards.len()).filter(|&i| !is_repeated_cloze(deck, i));
+        for (number, card) in notes.map(|i| &deck.cards[i]).enumerate() {
+            let question = match card.get_cloze() {
+                Some(_) => cloze_segments(&card.get_question(), None, true)
+                    .into_iter()
+                    .map(
+                        |(text, deleted)| {
+                            if deleted { format!("_{}_", text) } else { text }
+                        },
+                    )
+                    .collect(),
+                None => card.get_question(),
+            };
+            let mut question = question.lines().collect::<Vec<_>>().join("  \n   ");
+            if !card.get_tags().is_empty() {
+                question.push_str(&format!(" _({})_", card.get_tags().join(", ")));
+            }
+            output.push_str(&format!("{}. **{}**  \n", number + 1, question));
+            for line in card.get_answer().lines() {
+                output.push_str(&format!("   {}  \n", line));
+            }
+            output.push('\n');
+        }
+    }
+    output
+}
+
+fn is_repeated_cloze(deck: &Deck, index: usize) -> bool {
+    deck.cards[index].get_cloze().is_some() && deck.get_siblings(index).iter().any(|&i| i < index)
+}
+
+fn is_repeated_note(deck: &Deck, index: usize, direction: CardDirection) -> bool {
+    deck.cards[index].is_reversed() && direction == CardDirection::Both
+        || is_repeated_cloze(deck, index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{CsvOptions, delimited, json};
+    use crate::model::FlashCard;
+    use crate::model::testing::{TempDir, card, deck, now, today};
+    use std::time::Duration;
+
+    fn sample() -> Collection {
+        let mut collection = Collection::default();
+        let mut deck = deck(&[("dom", "house")]);
+        deck.set_learning_steps(" / ".parse().unwrap());
+        let mut tricky = card("\"cudzysłów\", przecinek", "pierwsza\ndruga");
+        tricky.set_tags(["gramatyka", "b1"]);
+        deck.add_card(tricky);
+        collection.add_deck(deck);
+        collection.review_card(0, 0, 4, now(), Duration::ZERO);
+        collection
+    }
+
+    fn texts(deck: &Deck) -> Vec<(String, String, Vec<String>)> {
+        deck.cards
+            .iter()
+            .map(|card| {
+                (
+                    card.get_question(),
+                    card.get_answer(),
+                    card.get_tags().to_vec(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn csv_round_trips_text_and_tags() {
+        let collection = sample();
+        let dir = TempDir::new();
+        let path = dir.path().join("talia.csv");
+        assert_eq!(
+            export(&collection, Some(0), ExportFormat::Csv, &path).unwrap(),
+            2
+        );
+
+        let imported = delimited::import(&path, "Test", &CsvOptions::default(), today()).unwrap();
+        assert_eq!(imported.report.added, 2);
+        assert_eq!(texts(&imported.decks[0]), texts(&collection.decks[0]));
+    }
+
+    #[test]
+    fn csv_writes_one_row_per_note_with_its_direction() {
+        let mut collection = Collection::default();
+        let mut deck = deck(&[("dom", "house")]);
+        for (question, answer, direction) in [
+            ("pies", "dog", CardDirection::Both),
+            ("kot", "cat", CardDirection::Reverse),
+        ] {
+            let note = FlashCard::new_note(question.into(), answer.into(), direction, today());
+            for card in note.unwrap() {
+                deck.add_card(card);
+            }
+        }
+        collection.add_deck(deck);
+        let dir = TempDir::new();
+        let path = dir.path().join("talia.csv");
+        assert_eq!(
+            export(&collection, Some(0), ExportFormat::Csv, &path).unwrap(),
+            3
+        );
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 4);
+        assert!(contents.contains("pies,dog,,both,"));
+        assert!(contents.contains("kot,cat,,reverse,"));
+
+        let imported = delimited::import(&path, "Test", &CsvOptions::default(), today()).unwrap();
+        assert_eq!(imported.report.added, 4);
+        let (original, loaded) = (&collection.decks[0], &imported.decks[0]);
+        assert_eq!(texts(loaded), texts(original));
+        for i in 0..original.cards.len() {
+            assert_eq!(loaded.get_note_direction(i), original.get_note_direction(i));
+        }
+    }
+
+    #[test]
+    fn simple_json_round_trips_text() {
+        let collection = sample();
+        let dir = TempDir::new();
+        let path = dir.path().join("talia.json");
+        export(&collection, Some(0), ExportFormat::SimpleJson, &path).unwrap();
+
+        let imported = json::import(&path, "Test", CardDirection::Forward, today()).unwrap();
+        let questions: Vec<String> = imported.decks[0]
+            .cards
+            .iter()
+            .map(|card| card.get_question())
+            .collect();
+        assert_eq!(questions, vec!["dom", "\"cudzysłów\", przecinek"]);
+    }
+
+    #[test]
+    fn full_json_keeps_progress_and_history() {
+        let collection = sample();
+        let dir = TempDir::new();
+        let path = dir.path().join("kolekcja.full.json");
+        export(&collection, None, ExportFormat::FullJson, &path).unwrap();
+
+        let imported = json::import(&path, "", CardDirection::Forward, today()).unwrap();
+        let card = &imported.decks[0].cards[0];
+        assert_eq!(card.get_interval(), 1);
+        assert_eq!(card.get_id(), collection.decks[0].cards[0].get_id());
+        assert_eq!(imported.review_log.len(), 1);
+        assert_eq!(imported.review_log[0].card_id, card.get_id());
+    }
+
+    #[test]
+    fn detects_format_from_path() {
+        let format = |name: &str| ExportFormat::from_path(Path::new(name));
+        assert_eq!(format("kolekcja.full.json"), Some(ExportFormat::FullJson));
+        assert_eq!(format("talia.JSON"), Some(ExportFormat::SimpleJson));
+        assert_eq!(format("talia.apkg"), Some(ExportFormat::Anki));
+        assert_eq!(format("talia.txt"), None);
+    }
+}
@@ -0,0 +1,76 @@
+use super::Imported;
+use crate::model::{CardDirection, Deck, ImportReport, ReviewLogEntry};
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct FullImport {
+    decks: Vec<Deck>,
+    #[serde(default)]
+    review_log: Vec<ReviewLogEntry>,
+}
+
+pub fn import(
+    path: &Path,
+    name: &str,
+    direction: CardDirection,
+    today: NaiveDate,
+) -> Result<Imported> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Nie udało się otworzyć pliku: {}", path.display()))?;
+    let value: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Błąd podczas parsowania pliku JSON: {}", path.display()))?;
+    if !value.is_object() {
+        let (deck, report) =
+            Deck::new_from_file(&path.to_string_lossy(), name.to_string(), direction, today)?;
+        return Ok(Imported {
+            decks: vec![deck],
+            review_log: vec![],
+            report,
+        });
+    }
+    let full: FullImport = serde_json::from_value(value)
+        .with_context(|| format!("Błąd podczas parsowania pliku JSON: {}", path.display()))?;
+    let mut report = ImportReport::default();
+    let mut card_ids: HashSet<Uuid> = HashSet::new();
+    let mut decks = vec![];
+    let mut position = 0;
+    for mut deck in full.decks {
+        let mut imported = Deck::new(deck.get_name());
+        imported.set_scheduler(deck.get_scheduler());
+        for mut card in deck.cards.drain(..) {
+            position += 1;
+            if let Err(error) = card.set_text(card.get_question(), card.get_answer()) {
+                report.skip(position, error);
+                continue;
+            }
+            card_ids.insert(card.get_id());
+            imported.add_card(card);
+            report.added += 1;
+        }
+        decks.push(imported);
+    }
+    let review_log = full
+        .review_log
+        .into_iter()
+        .filter(|entry| card_ids.contains(&entry.card_id))
+        .map(|mut entry| {
+            entry.id = Uuid::new_v4();
+            entry
+        })
+        .collect();
+    if decks.len() == 1 && !name.trim().is_empty() {
+        decks[0].set_name(name.trim().to_string());
+    }
+    Ok(Imported {
+        decks,
+        review_log,
+        report,
+    })
+}
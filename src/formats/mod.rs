@@ -0,0 +1,43 @@
+mod anki;
+mod delimited;
+mod export;
+mod json;
+
+use crate::model::{Deck, ImportReport, ReviewLogEntry, SourceFormat};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::path::Path;
+
+pub use delimited::{Column, CsvOptions, parse_delimiter};
+pub use export::{ExportFormat, export};
+
+pub struct Imported {
+    pub decks: Vec<Deck>,
+    pub review_log: Vec<ReviewLogEntry>,
+    pub report: ImportReport,
+}
+
+pub fn source_format(path: &str) -> SourceFormat {
+    if anki::is_anki_package(Path::new(path)) {
+        SourceFormat::Anki
+    } else if delimited::is_delimited_text(Path::new(path)) {
+        SourceFormat::Csv
+    } else {
+        SourceFormat::Json
+    }
+}
+
+pub fn import_file(
+    path: &str,
+    name: &str,
+    csv_options: &CsvOptions,
+    today: NaiveDate,
+) -> Result<Imported> {
+    if anki::is_anki_package(Path::new(path)) {
+        return anki::import(Path::new(path), name, today);
+    }
+    if delimited::is_delimited_text(Path::new(path)) {
+        return delimited::import(Path::new(path), name, csv_options, today);
+    }
+    json::import(Path::new(path), name, csv_options.direction, today)
+}
@@ -1,121 +1,423 @@
 mod app;
+mod cli;
+mod formats;
 mod model;
+mod storage;
 mod ui;
 
-use crate::app::{App, CurrentScreen, CurrentlyEditing};
-use crate::model::Collection;
-use anyhow::Result;
+use crate::app::{App, CurrentScreen, CurrentlyEditing, NotificationKind};
+use crate::cli::{Cli, Command, ExportArgs, ImportArgs};
+use crate::formats::ExportFormat;
+use crate::model::CollectionError;
+use anyhow::{Result, bail};
+use clap::Parser;
 use ratatui::Terminal;
 use ratatui::backend::{Backend, CrosstermBackend};
-use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
 use ratatui::crossterm::{event, execute};
 use std::io;
+use std::io::{IsTerminal, Write};
+use std::panic;
+use std::path::Path;
+use std::time::Duration;
+
+const SOURCE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let data_dir = storage::resolve_data_dir(cli.data_dir.as_deref())?;
+    if cli.command.is_none() && io::stdin().is_terminal() {
+        offer_legacy_migration(&data_dir)?;
+    }
+    let opened = storage::open(&data_dir)?;
+    let mut app = App::new(opened.collection, opened.storage);
+    app.watch_sources = cli.watch;
+    app.requeue_gap = cli.requeue_gap;
+    match &cli.command {
+        Some(Command::Import(args)) => return import_from_cli(&mut app, args, opened.warnings),
+        Some(Command::Export(args)) => return export_from_cli(&app, args, opened.warnings),
+        None => {}
+    }
+    if !opened.warnings.is_empty() {
+        app.notify(
+            NotificationKind::Error,
+            "Odzyskiwanie danych",
+            opened.warnings,
+        );
+    }
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        default_hook(info);
+    }));
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    let collection = Collection::new()?;
-    let mut app = App::new(collection);
-    run_app(&mut terminal, &mut app)?;
+    let result = run_app(&mut terminal, &mut app);
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
 
-    app.collection.save()?;
-
+    result
+}
+fn import_from_cli(app: &mut App, args: &ImportArgs, warnings: Vec<String>) -> Result<()> {
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    let name = args.deck_name();
+    if name.trim().is_empty() {
+        return Err(CollectionError::EmptyDeckName.into());
+    }
+    let (added, lines) =
+        app.import_decks(&args.path.to_string_lossy(), &name, &args.csv_options())?;
+    for line in lines {
+        println!("{}", line);
+    }
+    if added == 0 {
+        bail!("Nie zaimportowano żadnej karty.");
+    }
+    Ok(())
+}
+fn export_from_cli(app: &App, args: &ExportArgs, warnings: Vec<String>) -> Result<()> {
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    let deck_index = match &args.deck {
+        Some(name) => match app
+            .collection
+            .decks
+            .iter()
+            .position(|d| d.get_name() == *name)
+        {
+            Some(idx) => Some(idx),
+            None => bail!("Nie ma talii o nazwie \"{}\".", name),
+        },
+        None => None,
+    };
+    let Some(format) = args.format.or_else(|| ExportFormat::from_path(&args.path)) else {
+        bail!("Nie rozpoznano formatu po rozszerzeniu pliku. Podaj go opcją --format.");
+    };
+    let count = formats::export(&app.collection, deck_index, format, &args.path)?;
+    println!("Zapisano kart: {} do pliku {}.", count, args.path.display());
+    Ok(())
+}
+fn offer_legacy_migration(data_dir: &Path) -> Result<()> {
+    let files = storage::find_legacy_files(Path::new("."), data_dir);
+    if files.is_empty() {
+        return Ok(());
+    }
+    for file in &files {
+        println!("Znaleziono dane w bieżącym katalogu: {}", file.display());
+    }
+    print!("Przenieść je do {}? [t/N] ", data_dir.display());
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer)? == 0 {
+        println!();
+        return Ok(());
+    }
+    if matches!(
+        answer.trim().to_lowercase().as_str(),
+        "t" | "tak" | "y" | "yes"
+    ) {
+        storage::move_legacy_files(&files, data_dir)?;
+    } else {
+        storage::decline_legacy_migration(data_dir)?;
+    }
     Ok(())
 }
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
     while app.current_screen != CurrentScreen::Exiting {
         terminal.draw(|f| ui::draw(f, app))?;
+        if !event::poll(SOURCE_CHECK_INTERVAL)? {
+            app.check_sources();
+            continue;
+        }
         if let Event::Key(key) = event::read()? {
             if key.kind == event::KeyEventKind::Release {
                 continue;
             }
-            match app.current_screen {
-                CurrentScreen::Main => match key.code {
-                    KeyCode::Char('q') => {
-                        app.current_screen = CurrentScreen::Exiting;
-                    }
-                    KeyCode::Down => app.select_next(),
-                    KeyCode::Up => app.select_previous(),
-                    KeyCode::Enter => app.toggle_study(),
-                    KeyCode::Char('a') => {
-                        app.current_screen = CurrentScreen::AddingDeck;
-                        app.toggle_editing();
+            app.message = None;
+            if !app.notifications.is_empty() {
+                if matches!(key.code, KeyCode::Enter | KeyCode::Esc | KeyCode::Char(' ')) {
+                    app.dismiss_notification();
+                }
+                continue;
+            }
+            if let Err(error) = handle_key(app, key) {
+                app.notify_error("Błąd", &error);
+            }
+        }
+    }
+    Ok(())
+}
+fn handle_key(app: &mut App, key: KeyEvent) -> Result<()> {
+    match app.current_screen {
+        CurrentScreen::Main if app.tags_focused => match key.code {
+            KeyCode::Down => app.tag_list_state.select_next(),
+            KeyCode::Up => app.tag_list_state.select_previous(),
+            KeyCode::Enter => app.select_tag(),
+            KeyCode::Tab | KeyCode::Esc => app.toggle_tags_focus(),
+            KeyCode::Char('q') => {
+                app.current_screen = CurrentScreen::Exiting;
+            }
+            _ => {}
+        },
+        CurrentScreen::Main => match key.code {
+            KeyCode::Char('q') => {
+                app.current_screen = CurrentScreen::Exiting;
+            }
+            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => app.undo()?,
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo()?,
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                app.move_selected_deck(false)?
+            }
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                app.move_selected_deck(true)?
+            }
+            KeyCode::Char('J') => app.move_selected_deck(false)?,
+            KeyCode::Char('K') => app.move_selected_deck(true)?,
+            KeyCode::Down => app.select_next(),
+            KeyCode::Up => app.select_previous(),
+            KeyCode::Left => app.collapse_selected_deck(),
+            KeyCode::Right => app.expand_selected_deck(),
+            KeyCode::Enter => app.toggle_study(),
+            KeyCode::Char('r') => app.start_rename(),
+            KeyCode::Char('d') => app.start_delete_deck(),
+            KeyCode::Char('m') => app.toggle_merge()?,
+            KeyCode::Esc if app.merge_source.is_some() => app.cancel_merge(),
+            KeyCode::Esc => app.clear_tag_filter(),
+            KeyCode::Tab => app.toggle_tags_focus(),
+            KeyCode::Char('f') => app.start_tag_filter(),
+            KeyCode::Char('w') => app.study_all_decks(),
+            KeyCode::Char('s') => app.toggle_scheduler()?,
+            KeyCode::Char('o') => app.start_limits(),
+            KeyCode::Char('p') => app.start_learning_steps(),
+            KeyCode::Char('i') => app.toggle_answer_mode()?,
+            KeyCode::Char('c') => app.start_quiz(),
+            KeyCode::Char('b') => app.open_browser(false),
+            KeyCode::Char('B') => app.open_browser(true),
+            KeyCode::Char('n') => app.open_editor_from_main(),
+            KeyCode::Char('t') => app.open_statistics(),
+            KeyCode::Char('e') => app.start_export(false),
+            KeyCode::Char('E') => app.start_export(true),
+            KeyCode::Char('u') => app.start_update_from_source(),
+            KeyCode::Char('l') => app.reload_from_source()?,
+            KeyCode::Char('a') => {
+                app.current_screen = CurrentScreen::AddingDeck;
+                app.toggle_editing();
+            }
+            _ => {}
+        },
+        CurrentScreen::Studying if app.is_quiz_practice() => match key.code {
+            KeyCode::Esc => app.finish_quiz(),
+            KeyCode::Char(value @ '1'..='9') if app.is_choosing_answer() => {
+                app.choose_answer(value as usize - '1' as usize)?
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => app.next_quiz_card(),
+            _ => {}
+        },
+        CurrentScreen::Studying if app.is_choosing_answer() => match key.code {
+            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => app.undo()?,
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo()?,
+            KeyCode::Esc => app.stop_studying(),
+            KeyCode::Char(' ') => app.toggle_answer(),
+            KeyCode::Char(value @ '1'..='9') => app.choose_answer(value as usize - '1' as usize)?,
+            _ => {}
+        },
+        CurrentScreen::Studying if app.is_typing_answer() => match key.code {
+            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => app.undo()?,
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo()?,
+            KeyCode::Esc => app.stop_studying(),
+            KeyCode::Enter => app.check_typed_answer(),
+            KeyCode::Backspace => app.edit_typed_answer(|answer| {
+                answer.pop();
+            }),
+            KeyCode::Char(value) => app.edit_typed_answer(|answer| answer.push(value)),
+            _ => {}
+        },
+        CurrentScreen::Studying => match key.code {
+            KeyCode::Esc => app.stop_studying(),
+            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => app.undo()?,
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo()?,
+            KeyCode::Char(' ') => app.toggle_answer(),
+            KeyCode::Enter => app.accept_suggested_rating()?,
+            KeyCode::Char('1') => app.rate_current_card(1)?,
+            KeyCode::Char('2') => app.rate_current_card(2)?,
+            KeyCode::Char('3') => app.rate_current_card(3)?,
+            KeyCode::Char('4') => app.rate_current_card(4)?,
+            KeyCode::Char('5') => app.rate_current_card(5)?,
+            _ => {}
+        },
+
+        CurrentScreen::Browsing
+            if app
+                .browser_state
+                .as_ref()
+                .is_some_and(|state| state.is_searching) =>
+        {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => {
+                    if let Some(state) = &mut app.browser_state {
+                        state.is_searching = false;
                     }
-                    _ => {}
-                },
-                CurrentScreen::Studying => match key.code {
-                    KeyCode::Esc => app.stop_studying(),
-                    KeyCode::Char(' ') => app.toggle_answer(),
-                    KeyCode::Char('1') => app.rate_current_card(1),
-                    KeyCode::Char('2') => app.rate_current_card(2),
-                    KeyCode::Char('3') => app.rate_current_card(3),
-                    KeyCode::Char('4') => app.rate_current_card(4),
-                    KeyCode::Char('5') => app.rate_current_card(5),
-                    _ => {}
-                },
+                }
+                KeyCode::Backspace => app.browser_edit_query(|query| {
+                    query.pop();
+                }),
+                KeyCode::Char(value) => app.browser_edit_query(|query| query.push(value)),
+                _ => {}
+            }
+        }
+        CurrentScreen::Browsing
+            if app
+                .browser_state
+                .as_ref()
+                .is_some_and(|state| state.confirm_delete) =>
+        {
+            match key.code {
+                KeyCode::Char('t') | KeyCode::Char('y') => app.browser_confirm_delete()?,
+                _ => app.browser_cancel_delete(),
+            }
+        }
+        CurrentScreen::Browsing => match key.code {
+            KeyCode::Esc => app.close_browser(),
+            KeyCode::Enter | KeyCode::Char('e') => app.open_editor_from_browser(false),
+            KeyCode::Char('n') => app.open_editor_from_browser(true),
+            KeyCode::Char('d') => app.browser_request_delete(),
+            KeyCode::Char(' ') => app.browser_toggle_mark(),
+            KeyCode::Char('x') => app.start_split(),
+            KeyCode::Char('k') => app.browser_cycle_direction()?,
+            KeyCode::Down => app.browser_select_next(),
+            KeyCode::Up => app.browser_select_previous(),
+            KeyCode::Right => {
+                if let Some(state) = &app.browser_state {
+                    app.browser_sort_by(state.sort_column.next());
+                }
+            }
+            KeyCode::Left => {
+                if let Some(state) = &app.browser_state {
+                    app.browser_sort_by(state.sort_column.previous());
+                }
+            }
+            KeyCode::Char('s') => app.browser_toggle_sort_direction(),
+            KeyCode::Char('f') => app.browser_cycle_filter(),
+            KeyCode::Char('/') => {
+                if let Some(state) = &mut app.browser_state {
+                    state.is_searching = true;
+                }
+            }
+            _ => {}
+        },
 
-                CurrentScreen::AddingDeck if key.kind == KeyEventKind::Press => match key.code {
-                    KeyCode::Enter => {
-                        if let Some(editing) = &app.currently_editing {
-                            match editing {
-                                CurrentlyEditing::Name => {
-                                    app.currently_editing = Some(CurrentlyEditing::Path);
-                                }
-                                CurrentlyEditing::Path => {
-                                    app.add_new_deck();
-                                    app.current_screen = CurrentScreen::Main;
-                                    app.currently_editing = None;
+        CurrentScreen::Statistics => match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => app.close_statistics(),
+            KeyCode::Tab => app.toggle_statistics_scope(),
+            _ => {}
+        },
+        CurrentScreen::SourceChanged => match key.code {
+            KeyCode::Char('t') | KeyCode::Char('y') => app.accept_source_change()?,
+            _ => app.decline_source_change()?,
+        },
+        CurrentScreen::UpdatePreview => match key.code {
+            KeyCode::Enter => app.apply_update()?,
+            KeyCode::Esc => app.cancel_update(),
+            KeyCode::Char('r') => app.toggle_retire_removed(),
+            KeyCode::Down => app.scroll_update_preview(true),
+            KeyCode::Up => app.scroll_update_preview(false),
+            _ => {}
+        },
+        CurrentScreen::DeckPrompt => match key.code {
+            KeyCode::Enter => app.submit_prompt()?,
+            KeyCode::Esc => app.cancel_prompt(),
+            KeyCode::Tab => app.cycle_export_format(),
+            KeyCode::Backspace => app.prompt_edit(|input| {
+                input.pop();
+            }),
+            KeyCode::Char(value) => app.prompt_edit(|input| input.push(value)),
+            _ => {}
+        },
+        CurrentScreen::ConfirmingDeckDeletion => match key.code {
+            KeyCode::Char('t') | KeyCode::Char('y') => app.confirm_delete_deck()?,
+            _ => app.current_screen = CurrentScreen::Main,
+        },
+        CurrentScreen::EditingCard => match key.code {
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.save_editor()?
+            }
+            KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.editor_cycle_direction()
+            }
+            KeyCode::Esc => app.close_editor(),
+            KeyCode::Tab => app.editor_toggle_focus(),
+            KeyCode::Enter => app.editor_edit(|text| text.push('\n')),
+            KeyCode::Backspace => app.editor_edit(|text| {
+                text.pop();
+            }),
+            KeyCode::Char(value) => app.editor_edit(|text| text.push(value)),
+            _ => {}
+        },
 
-                                    app.name_input.clear();
-                                    app.path_input.clear();
-                                }
-                            }
+        CurrentScreen::AddingDeck if key.kind == KeyEventKind::Press => match key.code {
+            KeyCode::Enter => {
+                if let Some(editing) = &app.currently_editing {
+                    match editing {
+                        CurrentlyEditing::Name => {
+                            app.currently_editing = Some(CurrentlyEditing::Path);
                         }
-                    }
-                    KeyCode::Backspace => {
-                        if let Some(editing) = &app.currently_editing {
-                            match editing {
-                                CurrentlyEditing::Name => {
-                                    app.name_input.pop();
-                                }
-                                CurrentlyEditing::Path => {
-                                    app.path_input.pop();
-                                }
-                            }
+                        CurrentlyEditing::Path | CurrentlyEditing::Options => {
+                            app.add_new_deck()?;
                         }
                     }
-                    KeyCode::Esc => {
-                        app.current_screen = CurrentScreen::Main;
-                        app.currently_editing = None;
-                    }
-                    KeyCode::Tab => {
-                        app.toggle_editing();
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(editing) = &app.currently_editing {
+                    match editing {
+                        CurrentlyEditing::Name => {
+                            app.name_input.pop();
+                        }
+                        CurrentlyEditing::Path => {
+                            app.path_input.pop();
+                        }
+                        CurrentlyEditing::Options => {
+                            app.options_input.pop();
+                        }
                     }
-                    KeyCode::Char(value) => {
-                        if let Some(editing) = &app.currently_editing {
-                            match editing {
-                                CurrentlyEditing::Name => {
-                                    app.name_input.push(value);
-                                }
-                                CurrentlyEditing::Path => {
-                                    app.path_input.push(value);
-                                }
-                            }
+                }
+            }
+            KeyCode::Esc => {
+                app.current_screen = CurrentScreen::Main;
+                app.currently_editing = None;
+            }
+            KeyCode::Tab => {
+                app.toggle_editing();
+            }
+            KeyCode::Char(value) => {
+                if let Some(editing) = &app.currently_editing {
+                    match editing {
+                        CurrentlyEditing::Name => {
+                            app.name_input.push(value);
+                        }
+                        CurrentlyEditing::Path => {
+                            app.path_input.push(value);
+                        }
+                        CurrentlyEditing::Options => {
+                            app.options_input.push(value);
                         }
                     }
-                    _ => {}
-                },
-                _ => {}
+                }
             }
-        }
+            _ => {}
+        },
+        _ => {}
     }
     Ok(())
 }
@@ -0,0 +1,322 @@
+use super::deck::Deck;
+use super::flashcard::{FlashCard, FlashCardError};
+use chrono::NaiveDate;
+use std::collections::{BTreeSet, HashSet};
+
+pub enum ClozePart<'a> {
+    Text(&'a str),
+    Deletion {
+        number: u32,
+        answer: &'a str,
+        hint: Option<&'a str>,
+    },
+}
+
+pub fn parse_cloze(text: &str) -> Vec<ClozePart<'_>> {
+    let mut parts = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("{{c") {
+        let after = &rest[start + 3..];
+        let digits = after.chars().take_while(|c| c.is_ascii_digit()).count();
+        let deletion = after[digits..].strip_prefix("::").and_then(|body| {
+            let number = after[..digits].parse::<u32>().ok()?;
+            let end = body.find("}}")?;
+            Some((number, &body[..end], &body[end + 2..]))
+        });
+        let Some((number, body, remainder)) = deletion else {
+            parts.push(ClozePart::Text(&rest[..start + 3]));
+            rest = after;
+            continue;
+        };
+        if start > 0 {
+            parts.push(ClozePart::Text(&rest[..start]));
+        }
+        let (answer, hint) = match body.split_once("::") {
+            Some((answer, hint)) => (answer, Some(hint)),
+            None => (body, None),
+        };
+        parts.push(ClozePart::Deletion {
+            number,
+            answer,
+            hint,
+        });
+        rest = remainder;
+    }
+    if !rest.is_empty() {
+        parts.push(ClozePart::Text(rest));
+    }
+    parts
+}
+
+pub fn cloze_numbers(text: &str) -> BTreeSet<u32> {
+    parse_cloze(text)
+        .into_iter()
+        .filter_map(|part| match part {
+            ClozePart::Deletion { number, .. } => Some(number),
+            ClozePart::Text(_) => None,
+        })
+        .collect()
+}
+
+pub fn is_cloze(text: &str) -> bool {
+    !cloze_numbers(text).is_empty()
+}
+
+pub(crate) fn validate_cloze(text: &str) -> Result<(), FlashCardError> {
+    let empty = parse_cloze(text)
+        .into_iter()
+        .any(|part| matches!(part, ClozePart::Deletion { answer, .. } if answer.trim().is_empty()));
+    if empty {
+        return Err(FlashCardError::BlankDeletion);
+    }
+    Ok(())
+}
+
+pub fn cloze_segments(text: &str, target: Option<u32>, reveal: bool) -> Vec<(String, bool)> {
+    parse_cloze(text)
+        .into_iter()
+        .map(|part| match part {
+            ClozePart::Text(text) => (text.to_string(), false),
+            ClozePart::Deletion { number, answer, .. } if target.is_some_and(|t| t != number) => {
+                (answer.to_string(), false)
+            }
+            ClozePart::Deletion { answer, .. } if reveal => (answer.to_string(), true),
+            ClozePart::Deletion { hint, .. } => (format!("[{}]", hint.unwrap_or("...")), true),
+        })
+        .collect()
+}
+
+impl FlashCard {
+    pub fn new_cloze(
+        text: String,
+        extra: String,
+        number: u32,
+        today: NaiveDate,
+    ) -> Result<FlashCard, FlashCardError> {
+        let mut card = FlashCard::new(text, extra, today)?;
+        card.cloze = Some(number);
+        Ok(card)
+    }
+    pub fn get_cloze(&self) -> Option<u32> {
+        self.cloze
+    }
+    pub fn render_question(&self) -> String {
+        match self.cloze {
+            Some(number) => cloze_segments(&self.question, Some(number), false)
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect(),
+            None => self.question.clone(),
+        }
+    }
+    pub fn render_answer(&self) -> String {
+        let Some(number) = self.cloze else {
+            return self.answer.clone();
+        };
+        let mut answer: String = cloze_segments(&self.question, Some(number), true)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        if !self.answer.trim().is_empty() {
+            answer.push('\n');
+            answer.push_str(&self.answer);
+        }
+        answer
+    }
+}
+
+impl Deck {
+    pub(crate) fn regenerate_cloze(
+        &mut self,
+        index: usize,
+        today: NaiveDate,
+    ) -> Result<(), FlashCardError> {
+        let Some(card) = self.cards.get(index) else {
+            return Ok(());
+        };
+        let numbers = cloze_numbers(&card.question);
+        if numbers.is_empty() && card.cloze.is_none() {
+            return Ok(());
+        }
+        let id = card.get_id();
+        let note_id = card.get_note_id();
+        let (text, extra) = (card.question.clone(), card.answer.clone());
+        let tags = card.tags.clone();
+        if numbers.is_empty() {
+            self.cards[index].cloze = None;
+            self.cards
+                .retain(|card| card.get_id() == id || card.get_note_id() != note_id);
+            return Ok(());
+        }
+        if self.cards[index].cloze.is_none() {
+            self.cards[index].reversed = false;
+            let used: HashSet<u32> = self
+                .get_siblings(index)
+                .into_iter()
+                .filter_map(|i| self.cards[i].cloze)
+                .collect();
+            self.cards[index].cloze = numbers.iter().find(|n| !used.contains(n)).copied();
+        }
+        let mut kept = HashSet::new();
+        self.cards.retain(|card| {
+            card.get_note_id() != note_id
+                || !card.reversed
+                    && card
+                        .cloze
+                        .is_some_and(|n| numbers.contains(&n) && kept.insert(n))
+        });
+        for number in numbers.into_iter().filter(|n| !kept.contains(n)) {
+            let mut card = FlashCard::new_cloze(text.clone(), extra.clone(), number, today)?;
+            card.note_id = Some(note_id);
+            card.tags = tags.clone();
+            self.cards.push(card);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::note::CardDirection;
+    use crate::model::testing::{card, deck, today};
+
+    fn numbers_in(deck: &Deck) -> Vec<Option<u32>> {
+        let mut numbers: Vec<_> = deck.cards.iter().map(|card| card.cloze).collect();
+        numbers.sort();
+        numbers
+    }
+
+    #[test]
+    fn parses_deletions_and_hints() {
+        let parts = parse_cloze("Stolica {{c1::Polski}} to {{c2::Warszawa::miasto}}.");
+        assert_eq!(parts.len(), 5);
+        assert!(matches!(parts[0], ClozePart::Text("Stolica ")));
+        assert!(matches!(
+            parts[1],
+            ClozePart::Deletion {
+                number: 1,
+                answer: "Polski",
+                hint: None
+            }
+        ));
+        assert!(matches!(
+            parts[3],
+            ClozePart::Deletion {
+                number: 2,
+                answer: "Warszawa",
+                hint: Some("miasto")
+            }
+        ));
+        assert!(matches!(parts[4], ClozePart::Text(".")));
+    }
+
+    #[test]
+    fn ignores_malformed_markers() {
+        assert!(cloze_numbers("{{c::a}} {{cx::b}} {{c1::bez końca").is_empty());
+        assert!(!is_cloze("zwykłe pytanie"));
+        let numbers = cloze_numbers("{{c1::a}} {{c3::b}} {{c1::c}}");
+        assert_eq!(numbers.into_iter().collect::<Vec<_>>(), vec![1, 3]);
+    }
+
+    #[test]
+    fn rejects_blank_deletion() {
+        assert!(matches!(
+            validate_cloze("{{c1:: }} i {{c2::b}}"),
+            Err(FlashCardError::BlankDeletion)
+        ));
+        assert!(validate_cloze("{{c1::a}}").is_ok());
+    }
+
+    #[test]
+    fn renders_only_target_deletion() {
+        let card = FlashCard::new_cloze(
+            "{{c1::Ala}} ma {{c2::kota::zwierzę}}".into(),
+            "dodatek".into(),
+            2,
+            today(),
+        )
+        .unwrap();
+        assert_eq!(card.render_question(), "Ala ma [zwierzę]");
+        assert_eq!(card.render_answer(), "Ala ma kota\ndodatek");
+
+        let card =
+            FlashCard::new_cloze("{{c1::Ala}} ma kota".into(), "".into(), 1, today()).unwrap();
+        assert_eq!(card.render_question(), "[...] ma kota");
+        assert_eq!(card.render_answer(), "Ala ma kota");
+    }
+
+    #[test]
+    fn creates_sibling_per_number() {
+        let cards = FlashCard::new_note(
+            "{{c1::a}} {{c2::b}} {{c2::c}}".into(),
+            "".into(),
+            CardDirection::Both,
+            today(),
+        )
+        .unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].cloze, Some(1));
+        assert_eq!(cards[1].cloze, Some(2));
+        assert!(cards[0].note_id.is_some());
+        assert_eq!(cards[0].note_id, cards[1].note_id);
+        assert!(cards.iter().all(|card| !card.reversed));
+
+        let cards = FlashCard::new_note(
+            "{{c1::a}}".into(),
+            "".into(),
+            CardDirection::Forward,
+            today(),
+        )
+        .unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].note_id, None);
+    }
+
+    #[test]
+    fn regenerates_siblings_after_edit() {
+        let mut deck = deck(&[]);
+        for card in FlashCard::new_note(
+            "{{c1::a}} {{c2::b}}".into(),
+            "".into(),
+            CardDirection::Forward,
+            today(),
+        )
+        .unwrap()
+        {
+            deck.add_card(card);
+        }
+        deck.add_card(card("inne", "karta"));
+
+        let text = "{{c1::a}} {{c3::c}}".to_string();
+        deck.cards[0].set_text(text.clone(), "".into()).unwrap();
+        deck.sync_siblings(0);
+        deck.regenerate_cloze(0, today()).unwrap();
+        assert_eq!(numbers_in(&deck), vec![None, Some(1), Some(3)]);
+        assert!(
+            deck.cards
+                .iter()
+                .filter(|c| c.cloze.is_some())
+                .all(|c| c.question == text)
+        );
+        assert_eq!(deck.get_siblings(0).len(), 1);
+
+        deck.cards[0]
+            .set_text("bez luk".into(), "odp".into())
+            .unwrap();
+        deck.sync_siblings(0);
+        deck.regenerate_cloze(0, today()).unwrap();
+        assert_eq!(numbers_in(&deck), vec![None, None]);
+    }
+
+    #[test]
+    fn turns_plain_card_into_cloze_note() {
+        let mut deck = deck(&[("pytanie", "odp")]);
+        deck.cards[0]
+            .set_text("{{c1::a}} {{c2::b}}".into(), "".into())
+            .unwrap();
+        deck.regenerate_cloze(0, today()).unwrap();
+        assert_eq!(numbers_in(&deck), vec![Some(1), Some(2)]);
+        assert_eq!(deck.get_siblings(0).len(), 1);
+    }
+}
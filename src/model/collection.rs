@@ -1,44 +1,244 @@
 use crate::model::deck::Deck;
-use anyhow::{Context, Result, anyhow};
+use crate::model::flashcard::FlashCard;
+use crate::model::quiz::QuizLogEntry;
+use crate::model::review_log::ReviewLogEntry;
+use crate::model::stats::Statistics;
+use crate::model::tree::normalize_deck_name;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io;
-use std::io::{BufReader, Write};
-use std::path::Path;
+use std::collections::HashSet;
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
 
-const FILENAME: &'static str = "./deck.json";
+#[derive(Debug, Error)]
+pub enum CollectionError {
+    #[error("Nazwa talii nie może być pusta.")]
+    EmptyDeckName,
+    #[error("Nie można scalić talii z samą sobą.")]
+    MergeIntoItself,
+    #[error("Nie zaznaczono żadnych kart do przeniesienia.")]
+    NoCardsSelected,
+    #[error("Nie można przenieść talii do jej własnej podtalii.")]
+    MoveIntoDescendant,
+}
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Collection {
     pub decks: Vec<Deck>,
+    #[serde(default)]
+    pub(crate) review_log: Vec<ReviewLogEntry>,
+    #[serde(default)]
+    pub(crate) quiz_log: Vec<QuizLogEntry>,
 }
 
 impl Collection {
-    pub fn save(&self) -> Result<()> {
-        let serialized = serde_json::to_string(self)?;
-        let path = Path::new(FILENAME);
-        let mut file = File::create(path)?;
-        file.write_all(serialized.as_bytes())?;
-        Ok(())
-    }
-    pub fn new() -> Result<Collection> {
-        match File::open(FILENAME) {
-            Ok(file) => {
-                let reader = BufReader::new(file);
-                let collection: Collection = serde_json::from_reader(reader)
-                    .context("Błąd podczas parsowania pliku JSON.")?;
-                Ok(collection)
-            }
-            Err(error) => {
-                if error.kind() == io::ErrorKind::NotFound {
-                    Ok(Collection::default())
-                } else {
-                    Err(anyhow!(error))
-                }
-            }
+    pub fn add_deck(&mut self, mut deck: Deck) {
+        deck.name = normalize_deck_name(&deck.name);
+        self.decks.push(deck);
+    }
+    pub fn remove_deck(&mut self, index: usize) -> Option<Deck> {
+        if index < self.decks.len() {
+            Some(self.decks.remove(index))
+        } else {
+            None
+        }
+    }
+    pub fn merge_decks(&mut self, source: usize, target: usize) -> Result<usize, CollectionError> {
+        if source == target {
+            return Err(CollectionError::MergeIntoItself);
+        }
+        let ids: Vec<Uuid> = self.decks[source]
+            .cards
+            .iter()
+            .map(|c| c.get_id())
+            .collect();
+        let from = self.decks[source].get_scheduler();
+        let cards = self.decks[source].take_cards(&ids);
+        self.decks[target].append_cards(cards, from);
+        self.decks.remove(source);
+        Ok(if source < target { target - 1 } else { target })
+    }
+    pub fn split_deck(
+        &mut self,
+        index: usize,
+        card_ids: &[Uuid],
+        name: &str,
+    ) -> Result<usize, CollectionError> {
+        let name = normalize_deck_name(name);
+        if name.is_empty() {
+            return Err(CollectionError::EmptyDeckName);
+        }
+        let cards = self.decks[index].take_cards(card_ids);
+        if cards.is_empty() {
+            return Err(CollectionError::NoCardsSelected);
         }
+        let scheduler = self.decks[index].get_scheduler();
+        let mut deck = Deck::new(name);
+        deck.set_scheduler(scheduler);
+        deck.append_cards(cards, scheduler);
+        self.decks.insert(index + 1, deck);
+        Ok(index + 1)
     }
+    pub fn review_card(
+        &mut self,
+        deck_index: usize,
+        card_index: usize,
+        rating: u32,
+        now: NaiveDateTime,
+        time_spent: Duration,
+    ) -> Option<&ReviewLogEntry> {
+        let deck = self.decks.get_mut(deck_index)?;
+        let before = deck.get_card(card_index)?.clone();
+        deck.review_card(card_index, rating, now);
+        let after = deck.get_card(card_index)?;
+        self.review_log.push(ReviewLogEntry {
+            id: Uuid::new_v4(),
+            card_id: after.get_id(),
+            timestamp: now,
+            rating,
+            previous_interval: before.get_interval(),
+            new_interval: after.get_interval(),
+            previous_ease: before.get_ef(),
+            new_ease: after.get_ef(),
+            time_spent_ms: time_spent.as_millis() as u64,
+            undoes: None,
+        });
+        self.review_log.last()
+    }
+    pub fn import_reviews(&mut self, entries: Vec<ReviewLogEntry>) {
+        self.review_log.extend(entries);
+    }
+    pub fn contains_card(&self, id: Uuid) -> bool {
+        self.decks
+            .iter()
+            .any(|deck| deck.cards.iter().any(|card| card.get_id() == id))
+    }
+    pub fn get_review_log(&self) -> &[ReviewLogEntry] {
+        &self.review_log
+    }
+    pub fn get_effective_reviews(&self) -> impl Iterator<Item = &ReviewLogEntry> {
+        let undone: HashSet<Uuid> = self.review_log.iter().filter_map(|e| e.undoes).collect();
+        self.review_log
+            .iter()
+            .filter(move |e| e.undoes.is_none() && !undone.contains(&e.id))
+    }
+    pub fn statistics(&self, deck_index: Option<usize>, today: NaiveDate) -> Statistics {
+        let deck = deck_index.and_then(|idx| self.decks.get(idx));
+        let ids: Option<HashSet<Uuid>> =
+            deck.map(|deck| deck.cards.iter().map(|c| c.get_id()).collect());
+        let in_scope = |card_id: &Uuid| ids.as_ref().is_none_or(|ids| ids.contains(card_id));
+        let cards: Box<dyn Iterator<Item = &FlashCard>> = match deck {
+            Some(deck) => Box::new(deck.cards.iter()),
+            None => Box::new(self.decks.iter().flat_map(|d| d.cards.iter())),
+        };
+        let mut stats = Statistics::compute(
+            cards,
+            self.get_effective_reviews()
+                .filter(|e| in_scope(&e.card_id)),
+            today,
+        );
+        stats.add_quiz_answers(self.quiz_log.iter().filter(|e| in_scope(&e.card_id)));
+        stats
+    }
+}
 
-    pub fn add_deck(&mut self, deck: Deck) {
-        self.decks.push(deck);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::scheduler::SchedulerKind;
+    use crate::model::testing::{deck, now};
+
+    fn scheduled_deck(kind: SchedulerKind, question: &str) -> Deck {
+        let mut deck = deck(&[(question, "odpowiedź")]);
+        deck.set_scheduler(kind);
+        let card = &mut deck.cards[0];
+        (card.ef, card.stability, card.difficulty) = (2.166, 42.56, 6.3);
+        deck
+    }
+
+    fn scheduling(card: &FlashCard) -> (f32, f32, f32) {
+        (card.ef, card.stability, card.difficulty)
+    }
+
+    #[test]
+    fn every_rating_appends_a_log_entry() {
+        let mut collection = Collection::default();
+        let mut deck = deck(&[("dom", "house"), ("kot", "cat")]);
+        deck.set_learning_steps(" / ".parse().unwrap());
+        collection.add_deck(deck);
+        let first = collection
+            .review_card(0, 0, 4, now(), Duration::from_millis(1500))
+            .unwrap()
+            .clone();
+        assert_eq!(
+            first.card_id,
+            collection.decks[0].get_card(0).unwrap().get_id()
+        );
+        assert_eq!((first.previous_interval, first.new_interval), (0, 1));
+        assert_eq!(first.time_spent_ms, 1500);
+
+        collection.review_card(0, 0, 1, now(), Duration::ZERO);
+        collection.review_card(0, 1, 5, now(), Duration::ZERO);
+        assert_eq!(collection.review_log.len(), 3);
+        assert_eq!(collection.review_log[0].id, first.id);
+        assert_eq!(collection.review_log[1].rating, 1);
+        assert_eq!(collection.review_log[1].previous_interval, 1);
+        assert_ne!(
+            collection.review_log[1].card_id,
+            collection.review_log[2].card_id
+        );
+    }
+
+    #[test]
+    fn rating_a_missing_card_logs_nothing() {
+        let mut collection = Collection::default();
+        collection.add_deck(deck(&[("dom", "house")]));
+        assert!(
+            collection
+                .review_card(0, 1, 4, now(), Duration::ZERO)
+                .is_none()
+        );
+        assert!(
+            collection
+                .review_card(1, 0, 4, now(), Duration::ZERO)
+                .is_none()
+        );
+        assert!(collection.review_log.is_empty());
+    }
+
+    #[test]
+    fn merging_with_the_same_scheduler_keeps_scheduling() {
+        for kind in [SchedulerKind::Sm2, SchedulerKind::Fsrs] {
+            let mut collection = Collection::default();
+            collection.add_deck(scheduled_deck(kind, "dom"));
+            collection.add_deck(scheduled_deck(kind, "kot"));
+            let target = collection.merge_decks(0, 1).unwrap();
+            let cards = &collection.decks[target].cards;
+            assert_eq!(cards.len(), 2);
+            assert_eq!(scheduling(&cards[1]), (2.166, 42.56, 6.3));
+        }
+    }
+
+    #[test]
+    fn splitting_keeps_scheduling() {
+        for kind in [SchedulerKind::Sm2, SchedulerKind::Fsrs] {
+            let mut collection = Collection::default();
+            collection.add_deck(scheduled_deck(kind, "dom"));
+            let id = collection.decks[0].cards[0].get_id();
+            let index = collection.split_deck(0, &[id], "Nowa").unwrap();
+            let deck = &collection.decks[index];
+            assert_eq!(deck.get_scheduler(), kind);
+            assert_eq!(scheduling(&deck.cards[0]), (2.166, 42.56, 6.3));
+        }
+    }
+
+    #[test]
+    fn merging_with_another_scheduler_migrates() {
+        let mut collection = Collection::default();
+        collection.add_deck(scheduled_deck(SchedulerKind::Sm2, "dom"));
+        collection.add_deck(scheduled_deck(SchedulerKind::Fsrs, "kot"));
+        let target = collection.merge_decks(0, 1).unwrap();
+        let moved = &collection.decks[target].cards[1];
+        assert_ne!(scheduling(moved), (2.166, 42.56, 6.3));
     }
 }
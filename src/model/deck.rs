@@ -1,29 +1,93 @@
 use super::flashcard::*;
+use super::import::ImportReport;
+use super::learning::LearningSteps;
+use super::limits::{DailyCounts, DeckLimits};
+use super::note::CardDirection;
+use super::scheduler::SchedulerKind;
+use super::source::DeckSource;
+use super::tags::TagExpr;
+use super::typing::AnswerMode;
 use anyhow::{Context, Result};
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
+use uuid::{Builder, Uuid};
 #[derive(Deserialize)]
 struct RawCard {
+    #[serde(default)]
+    id: Option<Uuid>,
     question: String,
+    #[serde(default)]
     answer: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    direction: Option<CardDirection>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+fn sibling_id(id: Uuid, index: usize) -> Uuid {
+    let digest = Sha1::digest(format!("{}/{}", id, index).as_bytes());
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Builder::from_sha1_bytes(bytes).into_uuid()
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Deck {
-    name: String,
-    cards: Vec<FlashCard>,
+    #[serde(default = "Uuid::new_v4")]
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) cards: Vec<FlashCard>,
+    #[serde(default)]
+    pub(crate) scheduler: SchedulerKind,
+    #[serde(default)]
+    pub(crate) source: Option<DeckSource>,
+    #[serde(default)]
+    pub(crate) limits: DeckLimits,
+    #[serde(default)]
+    pub(crate) daily: DailyCounts,
+    #[serde(default)]
+    pub(crate) steps: LearningSteps,
+    #[serde(default)]
+    pub(crate) answer_mode: AnswerMode,
 }
 impl Deck {
     pub fn new(name: String) -> Self {
         Self {
+            id: Uuid::new_v4(),
             name,
             cards: vec![],
+            scheduler: SchedulerKind::default(),
+            source: None,
+            limits: DeckLimits::default(),
+            daily: DailyCounts::default(),
+            steps: LearningSteps::default(),
+            answer_mode: AnswerMode::default(),
         }
     }
 
-    pub fn new_from_file(path: &str, name: String, today: NaiveDate) -> Result<Deck> {
+    pub(crate) fn without_cards(&self) -> Deck {
+        Deck {
+            id: self.id,
+            name: self.name.clone(),
+            cards: vec![],
+            scheduler: self.scheduler,
+            source: self.source.clone(),
+            limits: self.limits,
+            daily: self.daily,
+            steps: self.steps.clone(),
+            answer_mode: self.answer_mode,
+        }
+    }
+
+    pub fn new_from_file(
+        path: &str,
+        name: String,
+        direction: CardDirection,
+        today: NaiveDate,
+    ) -> Result<(Deck, ImportReport)> {
         let json_path = Path::new(path);
 
         let file = File::open(json_path)
@@ -31,28 +95,73 @@ impl Deck {
 
         let reader = BufReader::new(file);
 
-        let rawcards: Vec<RawCard> =
-            serde_json::from_reader(reader).context("Błąd podczas parsowania pliku JSON.")?;
+        let entries: Vec<serde_json::Value> = serde_json::from_reader(reader)
+            .with_context(|| format!("Błąd podczas parsowania pliku JSON: {}", path))?;
         let mut deck: Deck = Deck::new(name);
-        for rawcard in rawcards {
-            deck.add_card(FlashCard::new(rawcard.question, rawcard.answer, today)?);
+        let mut report = ImportReport::default();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let cards = serde_json::from_value::<RawCard>(entry)
+                .map_err(anyhow::Error::from)
+                .and_then(|raw| {
+                    let mut cards = FlashCard::new_note(
+                        raw.question,
+                        raw.answer,
+                        raw.direction.unwrap_or(direction),
+                        today,
+                    )?;
+                    if let Some(id) = raw.id {
+                        let note_id = (cards.len() > 1).then_some(id);
+                        for (k, card) in cards.iter_mut().enumerate() {
+                            card.id = if k == 0 { id } else { sibling_id(id, k) };
+                            card.note_id = note_id;
+                        }
+                    }
+                    for card in cards.iter_mut() {
+                        card.set_tags(raw.tags.iter().map(String::as_str));
+                    }
+                    Ok(cards)
+                });
+            match cards {
+                Ok(cards) => {
+                    report.added += cards.len();
+                    for card in cards {
+                        deck.add_card(card);
+                    }
+                }
+                Err(error) => report.skip(i + 1, format!("{:#}", error)),
+            }
         }
-        Ok(deck)
+        Ok((deck, report))
     }
     pub fn add_card(&mut self, card: FlashCard) {
         self.cards.push(card);
     }
 
-    pub fn get_review_count(&self, today: NaiveDate) -> usize {
-        self.get_cards_to_review_indices(today).len()
-    }
-    pub fn get_cards_to_review_indices(&self, today: NaiveDate) -> Vec<usize> {
-        self.cards
+    pub fn get_cards_to_review_indices(
+        &self,
+        now: NaiveDateTime,
+        filter: Option<&TagExpr>,
+    ) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .cards
             .iter()
             .enumerate()
-            .filter(|(_, el)| (*el).get_date() <= today)
+            .filter(|(_, el)| el.is_due(now) && filter.is_none_or(|f| f.matches(el)))
             .map(|(i, _)| i)
-            .collect()
+            .collect();
+        let mut by_date = indices.clone();
+        by_date.sort_by_key(|&i| self.cards[i].get_date());
+        let mut notes = HashSet::new();
+        let first_of_note: HashSet<usize> = by_date
+            .into_iter()
+            .filter(|&i| notes.insert(self.cards[i].get_note_id()))
+            .collect();
+        indices.retain(|i| first_of_note.contains(i));
+        self.apply_daily_limits(&mut indices, now.date());
+        indices
+    }
+    pub fn get_id(&self) -> Uuid {
+        self.id
     }
     pub fn get_name(&self) -> String {
         self.name.clone()
@@ -60,10 +169,115 @@ impl Deck {
     pub fn get_card_count(&self) -> usize {
         self.cards.len()
     }
+    pub fn get_card_index(&self, id: Uuid) -> Option<usize> {
+        self.cards.iter().position(|card| card.get_id() == id)
+    }
     pub fn get_card(&self, index: usize) -> Option<&FlashCard> {
         self.cards.get(index)
     }
-    pub fn get_card_mut(&mut self, index: usize) -> Option<&mut FlashCard> {
-        self.cards.get_mut(index)
+    pub fn update_card(
+        &mut self,
+        index: usize,
+        question: String,
+        answer: String,
+        today: NaiveDate,
+    ) -> Result<(), FlashCardError> {
+        if let Some(card) = self.cards.get_mut(index) {
+            card.set_text(question, answer)?;
+            self.sync_siblings(index);
+            self.regenerate_cloze(index, today)?;
+        }
+        Ok(())
+    }
+    pub fn remove_card(&mut self, index: usize) -> Option<FlashCard> {
+        if index < self.cards.len() {
+            Some(self.cards.remove(index))
+        } else {
+            None
+        }
+    }
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+    pub fn take_cards(&mut self, ids: &[Uuid]) -> Vec<FlashCard> {
+        let (taken, kept) = self
+            .cards
+            .drain(..)
+            .partition(|card| ids.contains(&card.get_id()));
+        self.cards = kept;
+        taken
+    }
+    pub fn append_cards(&mut self, cards: Vec<FlashCard>, from: SchedulerKind) {
+        let scheduler = self.scheduler.scheduler();
+        for mut card in cards {
+            if from != self.scheduler {
+                scheduler.migrate(&mut card);
+            }
+            self.cards.push(card);
+        }
+    }
+    pub fn get_scheduler(&self) -> SchedulerKind {
+        self.scheduler
+    }
+    pub fn set_scheduler(&mut self, kind: SchedulerKind) {
+        if self.scheduler == kind {
+            return;
+        }
+        let scheduler = kind.scheduler();
+        for card in &mut self.cards {
+            scheduler.migrate(card);
+        }
+        self.scheduler = kind;
+    }
+    pub fn get_source(&self) -> Option<&DeckSource> {
+        self.source.as_ref()
+    }
+    pub fn set_source(&mut self, source: DeckSource) {
+        self.source = Some(source);
+    }
+    pub fn review_card(&mut self, index: usize, rating: u32, now: NaiveDateTime) {
+        let today = now.date();
+        if let Some(card) = self.cards.get(index) {
+            let queue = card.get_queue();
+            self.apply_review(index, rating, now);
+            self.count_review(queue, today);
+            self.bury_siblings(index, today);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::{TempDir, today};
+    use std::fs;
+
+    #[test]
+    fn json_id_applies_to_every_card_of_a_note() {
+        let dir = TempDir::new();
+        let path = dir.path().join("talia.json");
+        let (both, cloze) = (Uuid::new_v4(), Uuid::new_v4());
+        let entries = serde_json::json!([
+            {"id": both, "question": "pies", "answer": "dog", "direction": "both"},
+            {"id": cloze, "question": "{{c1::Ala}} ma {{c2::kota}}"},
+        ]);
+        fs::write(&path, entries.to_string()).unwrap();
+        let load = || {
+            let path = path.to_str().unwrap();
+            Deck::new_from_file(path, "Test".into(), CardDirection::Forward, today())
+                .unwrap()
+                .0
+        };
+
+        let (first, second) = (load(), load());
+        let ids = |deck: &Deck| deck.cards.iter().map(|c| c.get_id()).collect::<Vec<_>>();
+        assert_eq!(ids(&first), ids(&second));
+        assert_eq!(
+            (first.cards[0].get_id(), first.cards[2].get_id()),
+            (both, cloze)
+        );
+        assert_eq!(ids(&first).into_iter().collect::<HashSet<_>>().len(), 4);
+        assert_eq!(first.get_siblings(0), vec![1]);
+        assert_eq!(first.get_siblings(2), vec![3]);
     }
 }
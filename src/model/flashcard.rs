@@ -1,34 +1,56 @@
-use chrono::{Duration, NaiveDate};
+use super::cloze::{is_cloze, validate_cloze};
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
 #[derive(Debug, Error)]
 pub enum FlashCardError {
     #[error("Pytanie w fiszce nie może być puste.")]
     EmptyQuestion,
     #[error("Odpowiedź w fiszce nie może być pusta.")]
     EmptyAnswer,
+    #[error("Luka w fiszce nie może być pusta.")]
+    BlankDeletion,
 }
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FlashCard {
-    id: Uuid,
-    question: String,
-    answer: String,
-    ef: f32,
-    repetitions: u32,
-    interval: u32,
-    last_review_date: NaiveDate,
-    next_review_date: NaiveDate,
+    pub(crate) id: Uuid,
+    pub(crate) question: String,
+    pub(crate) answer: String,
+    pub(crate) ef: f32,
+    pub(crate) repetitions: u32,
+    pub(crate) interval: u32,
+    #[serde(default)]
+    pub(crate) stability: f32,
+    #[serde(default)]
+    pub(crate) difficulty: f32,
+    #[serde(default)]
+    pub(crate) lapses: u32,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) note_id: Option<Uuid>,
+    #[serde(default)]
+    pub(crate) reversed: bool,
+    #[serde(default)]
+    pub(crate) cloze: Option<u32>,
+    #[serde(default)]
+    pub(crate) learning_step: Option<u32>,
+    #[serde(default)]
+    pub(crate) due_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub(crate) buried_until: Option<NaiveDate>,
+    pub(crate) last_review_date: NaiveDate,
+    pub(crate) next_review_date: NaiveDate,
 }
 impl FlashCard {
     pub fn new(question: String, answer: String, today: NaiveDate) -> Result<Self, FlashCardError> {
-        if question.trim().is_empty() {
-            return Err(FlashCardError::EmptyQuestion);
-        }
-        if answer.trim().is_empty() {
-            return Err(FlashCardError::EmptyAnswer);
-        }
+        FlashCard::validate(&question, &answer)?;
         Ok(FlashCard {
             id: Uuid::new_v4(),
             question,
@@ -36,28 +58,60 @@ impl FlashCard {
             ef: 2.5,
             repetitions: 0,
             interval: 0,
+            stability: 0.0,
+            difficulty: 0.0,
+            lapses: 0,
+            tags: vec![],
+            note_id: None,
+            reversed: false,
+            cloze: None,
+            learning_step: None,
+            due_at: None,
+            buried_until: None,
             last_review_date: today,
             next_review_date: today,
         })
     }
-    pub fn review(&mut self, quality: u32, today: NaiveDate) {
-        let q = quality as f32;
-        let mut new_ef = self.ef + (0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02));
-        new_ef = new_ef.max(1.3);
-        if quality < 3 {
-            self.repetitions = 0;
-            self.interval = 1;
-        } else {
-            self.interval = match self.interval {
-                0 => 1,
-                1 => 6,
-                _ => (self.interval as f32 * self.ef).round() as u32,
-            };
-            self.ef = new_ef;
-            self.repetitions += 1;
+    fn validate(question: &str, answer: &str) -> Result<(), FlashCardError> {
+        if question.trim().is_empty() {
+            return Err(FlashCardError::EmptyQuestion);
+        }
+        if is_cloze(question) {
+            return validate_cloze(question);
         }
-        self.last_review_date = today;
-        self.next_review_date = today + Duration::days(self.interval as i64);
+        if answer.trim().is_empty() {
+            return Err(FlashCardError::EmptyAnswer);
+        }
+        Ok(())
+    }
+    pub fn set_text(&mut self, question: String, answer: String) -> Result<(), FlashCardError> {
+        FlashCard::validate(&question, &answer)?;
+        self.question = question;
+        self.answer = answer;
+        Ok(())
+    }
+    pub fn set_tags<'a>(&mut self, tags: impl IntoIterator<Item = &'a str>) {
+        self.tags.clear();
+        for tag in tags.into_iter().map(normalize_tag) {
+            if !tag.is_empty() && !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+    pub fn is_new(&self) -> bool {
+        self.repetitions == 0 && self.interval == 0 && self.lapses == 0 && !self.is_learning()
+    }
+    pub fn get_id(&self) -> Uuid {
+        self.id
+    }
+    pub fn get_interval(&self) -> u32 {
+        self.interval
+    }
+    pub fn get_ef(&self) -> f32 {
+        self.ef
+    }
+    pub fn get_repetitions(&self) -> u32 {
+        self.repetitions
     }
     pub fn get_date(&self) -> NaiveDate {
         self.next_review_date
@@ -68,4 +122,7 @@ impl FlashCard {
     pub fn get_answer(&self) -> String {
         self.answer.clone()
     }
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
 }
@@ -0,0 +1,288 @@
+use super::collection::Collection;
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use super::review_log::ReviewLogEntry;
+use chrono::NaiveDateTime;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+struct CardChange {
+    index: usize,
+    before: FlashCard,
+    after: FlashCard,
+}
+
+struct DeckChange {
+    id: Uuid,
+    before: Option<(usize, Deck)>,
+    after: Option<(usize, Deck)>,
+    cards: Option<Vec<CardChange>>,
+}
+impl DeckChange {
+    fn compact(&mut self) {
+        let (Some((_, before)), Some((_, after))) = (&mut self.before, &mut self.after) else {
+            return;
+        };
+        let same_cards = before.cards.len() == after.cards.len()
+            && before
+                .cards
+                .iter()
+                .zip(&after.cards)
+                .all(|(b, a)| b.id == a.id);
+        if !same_cards {
+            return;
+        }
+        let cards = std::mem::take(&mut before.cards)
+            .into_iter()
+            .zip(std::mem::take(&mut after.cards))
+            .enumerate()
+            .filter(|(_, (b, a))| b != a)
+            .map(|(index, (before, after))| CardChange {
+                index,
+                before,
+                after,
+            })
+            .collect();
+        self.cards = Some(cards);
+    }
+}
+
+pub struct CollectionChange {
+    decks: Vec<DeckChange>,
+    reviews: Vec<ReviewLogEntry>,
+    known_decks: HashSet<Uuid>,
+    known_reviews: usize,
+}
+
+fn snapshot(collection: &Collection, id: Uuid, with_cards: bool) -> Option<(usize, Deck)> {
+    let i = collection.decks.iter().position(|deck| deck.id == id)?;
+    let deck = &collection.decks[i];
+    Some((
+        i,
+        if with_cards {
+            deck.clone()
+        } else {
+            deck.without_cards()
+        },
+    ))
+}
+
+impl CollectionChange {
+    pub fn begin(collection: &Collection, deck_indices: &[usize]) -> CollectionChange {
+        let decks = deck_indices
+            .iter()
+            .filter_map(|&i| collection.decks.get(i))
+            .map(|deck| DeckChange {
+                id: deck.id,
+                before: snapshot(collection, deck.id, true),
+                after: None,
+                cards: None,
+            })
+            .collect();
+        CollectionChange {
+            decks,
+            reviews: vec![],
+            known_decks: collection.decks.iter().map(|deck| deck.id).collect(),
+            known_reviews: collection.review_log.len(),
+        }
+    }
+    pub fn begin_cards(
+        collection: &Collection,
+        deck_index: usize,
+        card_indices: &[usize],
+    ) -> CollectionChange {
+        let mut change = CollectionChange::begin(collection, &[]);
+        if let Some(deck) = collection.decks.get(deck_index) {
+            let cards = card_indices
+                .iter()
+                .filter_map(|&index| {
+                    let card = deck.cards.get(index)?;
+                    Some(CardChange {
+                        index,
+                        before: card.clone(),
+                        after: card.clone(),
+                    })
+                })
+                .collect();
+            change.decks.push(DeckChange {
+                id: deck.id,
+                before: snapshot(collection, deck.id, false),
+                after: None,
+                cards: Some(cards),
+            });
+        }
+        change
+    }
+    pub fn finish(&mut self, collection: &Collection) {
+        for change in self.decks.iter_mut() {
+            match &mut change.cards {
+                Some(cards) => {
+                    change.after = snapshot(collection, change.id, false);
+                    let deck = collection.decks.iter().find(|deck| deck.id == change.id);
+                    for card in cards.iter_mut() {
+                        if let Some(after) = deck.and_then(|deck| deck.cards.get(card.index)) {
+                            card.after = after.clone();
+                        }
+                    }
+                    cards.retain(|card| card.before != card.after);
+                }
+                None => {
+                    change.after = snapshot(collection, change.id, true);
+                    change.compact();
+                }
+            }
+        }
+        for deck in &collection.decks {
+            if !self.known_decks.contains(&deck.id) {
+                self.decks.push(DeckChange {
+                    id: deck.id,
+                    before: None,
+                    after: snapshot(collection, deck.id, true),
+                    cards: None,
+                });
+            }
+        }
+        self.reviews =
+            collection.review_log[self.known_reviews.min(collection.review_log.len())..].to_vec();
+        self.known_decks.clear();
+    }
+    pub fn is_empty(&self) -> bool {
+        self.decks.is_empty() && self.reviews.is_empty()
+    }
+    pub fn get_deck_ids(&self) -> Vec<Uuid> {
+        self.decks.iter().map(|change| change.id).collect()
+    }
+    pub fn undo(&self, collection: &mut Collection, now: NaiveDateTime) -> Vec<ReviewLogEntry> {
+        self.restore(collection, true);
+        let undone: Vec<ReviewLogEntry> = self.reviews.iter().map(|e| e.undo(now)).collect();
+        collection.import_reviews(undone.clone());
+        undone
+    }
+    pub fn redo(&mut self, collection: &mut Collection) -> Vec<ReviewLogEntry> {
+        self.restore(collection, false);
+        self.reviews = self.reviews.iter().map(ReviewLogEntry::redo).collect();
+        collection.import_reviews(self.reviews.clone());
+        self.reviews.clone()
+    }
+    pub fn rollback(mut self, collection: &mut Collection) {
+        self.finish(collection);
+        self.restore(collection, true);
+    }
+    fn restore(&self, collection: &mut Collection, undo: bool) {
+        let ids: HashSet<Uuid> = self.get_deck_ids().into_iter().collect();
+        let (mut removed, kept): (Vec<Deck>, Vec<Deck>) = collection
+            .decks
+            .drain(..)
+            .partition(|deck| ids.contains(&deck.id));
+        collection.decks = kept;
+        let mut decks: Vec<(usize, Deck)> = vec![];
+        for change in &self.decks {
+            let state = if undo { &change.before } else { &change.after };
+            let Some((position, snapshot)) = state else {
+                continue;
+            };
+            let mut deck = snapshot.clone();
+            if let Some(cards) = &change.cards
+                && let Some(current) = removed.iter_mut().find(|d| d.id == change.id)
+            {
+                deck.cards = std::mem::take(&mut current.cards);
+                for card in cards {
+                    if let Some(target) = deck.cards.get_mut(card.index) {
+                        *target = if undo {
+                            card.before.clone()
+                        } else {
+                            card.after.clone()
+                        };
+                    }
+                }
+            }
+            decks.push((*position, deck));
+        }
+        decks.sort_by_key(|(position, _)| *position);
+        for (position, deck) in decks {
+            let position = position.min(collection.decks.len());
+            collection.decks.insert(position, deck);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::{card, collection, deck, now};
+    use std::time::Duration;
+
+    #[test]
+    fn undoes_and_redoes_a_rating() {
+        let mut collection = collection();
+        let before = collection.decks[0].cards[1].clone();
+        let log_len = collection.review_log.len();
+
+        let mut change = CollectionChange::begin_cards(&collection, 0, &[1]);
+        collection.review_card(0, 1, 4, now(), Duration::ZERO);
+        change.finish(&collection);
+        let after = collection.decks[0].cards[1].clone();
+        let original = collection.review_log[log_len].id;
+
+        let undone = change.undo(&mut collection, now());
+        assert_eq!(collection.decks[0].cards[1], before);
+        assert_eq!(undone.len(), 1);
+        assert_eq!(undone[0].undoes, Some(original));
+        assert_eq!(collection.review_log.len(), log_len + 2);
+
+        let redone = change.redo(&mut collection);
+        assert_eq!(collection.decks[0].cards[1], after);
+        assert_ne!(redone[0].id, original);
+        assert_eq!(redone[0].undoes, None);
+        assert_eq!(collection.review_log.len(), log_len + 3);
+    }
+
+    #[test]
+    fn undoes_deck_removal_in_place() {
+        let mut collection = collection();
+        let id = collection.decks[0].id;
+
+        let mut change = CollectionChange::begin(&collection, &[0]);
+        collection.remove_deck(0);
+        change.finish(&collection);
+
+        change.undo(&mut collection, now());
+        assert_eq!(collection.decks.len(), 2);
+        assert_eq!(collection.decks[0].id, id);
+        assert_eq!(collection.decks[0].cards.len(), 2);
+
+        change.redo(&mut collection);
+        assert!(collection.decks.iter().all(|deck| deck.id != id));
+    }
+
+    #[test]
+    fn undoes_deck_creation() {
+        let mut collection = collection();
+        let mut change = CollectionChange::begin(&collection, &[]);
+        collection.add_deck(deck(&[("ryba", "fish")]));
+        change.finish(&collection);
+        assert!(!change.is_empty());
+
+        change.undo(&mut collection, now());
+        assert_eq!(collection.decks.len(), 2);
+        change.redo(&mut collection);
+        assert_eq!(collection.decks.len(), 3);
+    }
+
+    #[test]
+    fn rollback_restores_the_snapshot() {
+        let mut collection = collection();
+        let before = collection.decks[0].cards.clone();
+
+        let change = CollectionChange::begin(&collection, &[0]);
+        collection.decks[0].cards[0].question = "zmienione".into();
+        collection.decks[0].add_card(card("ryba", "fish"));
+        change.rollback(&mut collection);
+        assert_eq!(collection.decks[0].cards, before);
+
+        let change = CollectionChange::begin(&collection, &[0]);
+        collection.decks[0].cards[1].answer = "zmienione".into();
+        change.rollback(&mut collection);
+        assert_eq!(collection.decks[0].cards, before);
+    }
+}
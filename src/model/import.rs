@@ -0,0 +1,37 @@
+const MAX_LISTED_SKIPPED: usize = 15;
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub added: usize,
+    pub skipped: Vec<SkippedEntry>,
+}
+#[derive(Debug)]
+pub struct SkippedEntry {
+    pub position: usize,
+    pub reason: String,
+}
+impl ImportReport {
+    pub fn skip(&mut self, position: usize, reason: impl ToString) {
+        self.skipped.push(SkippedEntry {
+            position,
+            reason: reason.to_string(),
+        });
+    }
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Dodano kart: {}, pominięto: {}.",
+            self.added,
+            self.skipped.len()
+        )];
+        for entry in self.skipped.iter().take(MAX_LISTED_SKIPPED) {
+            lines.push(format!("Element #{}: {}", entry.position, entry.reason));
+        }
+        if self.skipped.len() > MAX_LISTED_SKIPPED {
+            lines.push(format!(
+                "…oraz {} kolejnych pominiętych elementów.",
+                self.skipped.len() - MAX_LISTED_SKIPPED
+            ));
+        }
+        lines
+    }
+}
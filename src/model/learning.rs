@@ -0,0 +1,273 @@
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LearningStepsError {
+    #[error("Nieprawidłowy krok nauki: \"{0}\" (użyj np. 1m, 10m, 1h, 1d).")]
+    InvalidStep(String),
+    #[error("Oddziel kroki nauki od kroków ponownej nauki znakiem /, np. 1m 10m / 10m.")]
+    MissingSeparator,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LearningSteps {
+    pub learning: Vec<u32>,
+    pub relearning: Vec<u32>,
+}
+impl Default for LearningSteps {
+    fn default() -> Self {
+        LearningSteps {
+            learning: vec![1, 10],
+            relearning: vec![10],
+        }
+    }
+}
+
+pub fn format_step(minutes: u32) -> String {
+    match minutes {
+        m if m > 0 && m % 1440 == 0 => format!("{}d", m / 1440),
+        m if m > 0 && m % 60 == 0 => format!("{}h", m / 60),
+        m => format!("{}m", m),
+    }
+}
+
+pub fn format_steps(steps: &[u32]) -> String {
+    steps
+        .iter()
+        .map(|&step| format_step(step))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_step(text: &str) -> Result<u32, LearningStepsError> {
+    let invalid = || LearningStepsError::InvalidStep(text.to_string());
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => text.split_at(i),
+        None => (text, "m"),
+    };
+    let number: u32 = number.parse().map_err(|_| invalid())?;
+    let multiplier = match unit.to_lowercase().as_str() {
+        "m" | "min" => 1,
+        "h" | "g" => 60,
+        "d" => 1440,
+        _ => return Err(invalid()),
+    };
+    match number.checked_mul(multiplier) {
+        Some(minutes) if minutes > 0 => Ok(minutes),
+        _ => Err(invalid()),
+    }
+}
+
+pub fn parse_steps(text: &str) -> Result<Vec<u32>, LearningStepsError> {
+    text.split([' ', ','])
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| parse_step(part.trim()))
+        .collect()
+}
+
+impl fmt::Display for LearningSteps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} / {}",
+            format_steps(&self.learning),
+            format_steps(&self.relearning)
+        )
+    }
+}
+impl FromStr for LearningSteps {
+    type Err = LearningStepsError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (learning, relearning) = s
+            .split_once('/')
+            .ok_or(LearningStepsError::MissingSeparator)?;
+        Ok(LearningSteps {
+            learning: parse_steps(learning)?,
+            relearning: parse_steps(relearning)?,
+        })
+    }
+}
+
+impl FlashCard {
+    pub fn is_learning(&self) -> bool {
+        self.learning_step.is_some()
+    }
+    pub fn get_due_at(&self) -> Option<NaiveDateTime> {
+        self.due_at
+    }
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        if self.is_buried(now.date()) {
+            return false;
+        }
+        match self.due_at {
+            Some(due_at) => due_at <= now,
+            None => self.next_review_date <= now.date(),
+        }
+    }
+    fn set_learning_step(&mut self, step: usize, steps: &[u32], now: NaiveDateTime) {
+        let due_at = now + Duration::minutes(steps[step] as i64);
+        self.learning_step = Some(step as u32);
+        self.due_at = Some(due_at);
+        self.next_review_date = due_at.date();
+    }
+    fn graduate(&mut self) {
+        self.learning_step = None;
+        self.due_at = None;
+    }
+}
+
+impl Deck {
+    pub fn get_learning_steps(&self) -> &LearningSteps {
+        &self.steps
+    }
+    pub fn set_learning_steps(&mut self, steps: LearningSteps) {
+        self.steps = steps;
+    }
+    pub(crate) fn apply_review(&mut self, index: usize, rating: u32, now: NaiveDateTime) {
+        let scheduler = self.scheduler.scheduler();
+        let today = now.date();
+        let Some(card) = self.cards.get_mut(index) else {
+            return;
+        };
+        let step = match card.learning_step {
+            Some(step) => step as usize,
+            None if card.is_new() && !self.steps.learning.is_empty() => 0,
+            None => {
+                scheduler.review(card, rating, today);
+                if rating < 3 && !self.steps.relearning.is_empty() {
+                    card.set_learning_step(0, &self.steps.relearning, now);
+                }
+                return;
+            }
+        };
+        let relearning = card.lapses > 0;
+        let steps = if relearning {
+            &self.steps.relearning
+        } else {
+            &self.steps.learning
+        };
+        let next = match rating {
+            0..=2 => Some(0),
+            3 => Some(step),
+            4 => Some(step + 1),
+            _ => None,
+        };
+        match next.filter(|&next| next < steps.len()) {
+            Some(next) => card.set_learning_step(next, steps, now),
+            None => {
+                card.graduate();
+                if relearning {
+                    card.last_review_date = today;
+                    card.next_review_date = today + Duration::days(card.interval.max(1) as i64);
+                } else {
+                    scheduler.review(card, rating, today);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::{deck, now, today};
+
+    fn deck_with_card() -> Deck {
+        deck(&[("pytanie", "odpowiedź")])
+    }
+
+    #[test]
+    fn parses_steps_with_units() {
+        let steps: LearningSteps = "1m 10 1h, 2d / 15m".parse().unwrap();
+        assert_eq!(steps.learning, vec![1, 10, 60, 2880]);
+        assert_eq!(steps.relearning, vec![15]);
+        assert_eq!(steps.to_string(), "1m 10m 1h 2d / 15m");
+    }
+
+    #[test]
+    fn allows_empty_side() {
+        let steps: LearningSteps = " / 10m".parse().unwrap();
+        assert!(steps.learning.is_empty());
+        assert_eq!(steps.relearning, vec![10]);
+    }
+
+    #[test]
+    fn rejects_invalid_steps() {
+        assert!(matches!(
+            "1m 10m".parse::<LearningSteps>(),
+            Err(LearningStepsError::MissingSeparator)
+        ));
+        for text in ["5x / 10m", "0m / 10m", "m / 10m", "1m / -3"] {
+            assert!(matches!(
+                text.parse::<LearningSteps>(),
+                Err(LearningStepsError::InvalidStep(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn new_card_goes_through_steps() {
+        let mut deck = deck_with_card();
+        deck.apply_review(0, 3, now());
+        let card = &deck.cards[0];
+        assert_eq!(card.learning_step, Some(0));
+        assert_eq!(card.get_due_at(), Some(now() + Duration::minutes(1)));
+
+        deck.apply_review(0, 4, now());
+        let card = &deck.cards[0];
+        assert_eq!(card.learning_step, Some(1));
+        assert!(!card.is_due(now() + Duration::minutes(9)));
+        assert!(card.is_due(now() + Duration::minutes(10)));
+
+        deck.apply_review(0, 4, now());
+        let card = &deck.cards[0];
+        assert!(!card.is_learning());
+        assert!(card.get_interval() >= 1);
+    }
+
+    #[test]
+    fn step_crossing_midnight_is_due_by_time() {
+        let late = today().and_hms_opt(23, 55, 0).unwrap();
+        let mut deck = deck_with_card();
+        deck.apply_review(0, 4, late);
+        let card = &deck.cards[0];
+        assert_eq!(card.get_date(), today().succ_opt().unwrap());
+        assert!(!card.is_due(late + Duration::minutes(9)));
+        assert!(card.is_due(late + Duration::minutes(10)));
+    }
+
+    #[test]
+    fn ratings_move_between_steps() {
+        let mut deck = deck_with_card();
+        deck.set_learning_steps("1m 10m 1h / 10m".parse().unwrap());
+        deck.apply_review(0, 4, now());
+        assert_eq!(deck.cards[0].learning_step, Some(1));
+        deck.apply_review(0, 3, now());
+        assert_eq!(deck.cards[0].learning_step, Some(1));
+        deck.apply_review(0, 1, now());
+        assert_eq!(deck.cards[0].learning_step, Some(0));
+        deck.apply_review(0, 5, now());
+        assert!(!deck.cards[0].is_learning());
+    }
+
+    #[test]
+    fn lapse_enters_relearning() {
+        let mut deck = deck_with_card();
+        deck.apply_review(0, 5, now());
+        deck.apply_review(0, 1, now());
+        let card = &deck.cards[0];
+        assert_eq!(card.learning_step, Some(0));
+        assert!(card.lapses > 0);
+        assert_eq!(card.get_due_at(), Some(now() + Duration::minutes(10)));
+
+        deck.apply_review(0, 4, now());
+        let card = &deck.cards[0];
+        assert!(!card.is_learning());
+        assert!(card.get_date() > now().date());
+    }
+}
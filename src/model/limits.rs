@@ -0,0 +1,219 @@
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use super::tags::TagExpr;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DeckLimitsError {
+    #[error("Podaj dwie liczby: limit nowych kart i limit powtórek, np. 20/200.")]
+    InvalidFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct DeckLimits {
+    pub new_per_day: u32,
+    pub reviews_per_day: u32,
+}
+impl Default for DeckLimits {
+    fn default() -> Self {
+        DeckLimits {
+            new_per_day: 20,
+            reviews_per_day: 200,
+        }
+    }
+}
+impl fmt::Display for DeckLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.new_per_day, self.reviews_per_day)
+    }
+}
+impl FromStr for DeckLimits {
+    type Err = DeckLimitsError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers: Vec<u32> = s
+            .split(['/', ' ', ','])
+            .filter(|part| !part.trim().is_empty())
+            .map(|part| part.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| DeckLimitsError::InvalidFormat)?;
+        match numbers[..] {
+            [new_per_day, reviews_per_day] => Ok(DeckLimits {
+                new_per_day,
+                reviews_per_day,
+            }),
+            _ => Err(DeckLimitsError::InvalidFormat),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct DailyCounts {
+    pub date: Option<NaiveDate>,
+    pub new: u32,
+    pub reviews: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StudyCounts {
+    pub new: usize,
+    pub learning: usize,
+    pub review: usize,
+}
+impl std::ops::Add for StudyCounts {
+    type Output = StudyCounts;
+    fn add(self, other: StudyCounts) -> StudyCounts {
+        StudyCounts {
+            new: self.new + other.new,
+            learning: self.learning + other.learning,
+            review: self.review + other.review,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CardQueue {
+    New,
+    Learning,
+    Review,
+}
+impl FlashCard {
+    pub fn get_queue(&self) -> CardQueue {
+        if self.is_new() {
+            CardQueue::New
+        } else if self.is_learning() {
+            CardQueue::Learning
+        } else {
+            CardQueue::Review
+        }
+    }
+}
+
+impl Deck {
+    pub fn get_limits(&self) -> DeckLimits {
+        self.limits
+    }
+    pub fn set_limits(&mut self, limits: DeckLimits) {
+        self.limits = limits;
+    }
+    pub fn get_daily_counts(&self, today: NaiveDate) -> DailyCounts {
+        match self.daily.date {
+            Some(date) if date == today => self.daily,
+            _ => DailyCounts {
+                date: Some(today),
+                ..DailyCounts::default()
+            },
+        }
+    }
+    pub(crate) fn count_review(&mut self, queue: CardQueue, today: NaiveDate) {
+        let mut daily = self.get_daily_counts(today);
+        match queue {
+            CardQueue::New => daily.new += 1,
+            CardQueue::Review => daily.reviews += 1,
+            CardQueue::Learning => {}
+        }
+        self.daily = daily;
+    }
+    pub(crate) fn apply_daily_limits(&self, indices: &mut Vec<usize>, today: NaiveDate) {
+        let daily = self.get_daily_counts(today);
+        let mut new_left = self.limits.new_per_day.saturating_sub(daily.new);
+        let mut reviews_left = self.limits.reviews_per_day.saturating_sub(daily.reviews);
+        indices.retain(|&i| {
+            let left = match self.cards[i].get_queue() {
+                CardQueue::New => &mut new_left,
+                CardQueue::Review => &mut reviews_left,
+                CardQueue::Learning => return true,
+            };
+            if *left == 0 {
+                return false;
+            }
+            *left -= 1;
+            true
+        });
+    }
+    pub fn get_study_counts(&self, now: NaiveDateTime, filter: Option<&TagExpr>) -> StudyCounts {
+        let mut counts = StudyCounts::default();
+        for i in self.get_cards_to_review_indices(now, filter) {
+            match self.cards[i].get_queue() {
+                CardQueue::New => counts.new += 1,
+                CardQueue::Learning => counts.learning += 1,
+                CardQueue::Review => counts.review += 1,
+            }
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::{card, deck, now, today};
+    use chrono::Duration;
+
+    fn deck_of(count: usize, limits: &str) -> Deck {
+        let mut deck = deck(&[]);
+        for i in 0..count {
+            deck.add_card(card(&format!("pytanie {}", i), "odpowiedź"));
+        }
+        deck.set_limits(limits.parse().unwrap());
+        deck.set_learning_steps(" / ".parse().unwrap());
+        deck
+    }
+
+    #[test]
+    fn parses_limits() {
+        for text in ["5/50", "5 50", " 5 , 50 "] {
+            let limits: DeckLimits = text.parse().unwrap();
+            assert_eq!(limits.to_string(), "5/50");
+        }
+        for text in ["", "5", "a/b", "1/2/3", "-1/5"] {
+            assert!(text.parse::<DeckLimits>().is_err());
+        }
+    }
+
+    #[test]
+    fn limits_new_cards_per_day() {
+        let mut deck = deck_of(5, "2/200");
+        assert_eq!(deck.get_cards_to_review_indices(now(), None).len(), 2);
+
+        deck.review_card(0, 4, now());
+        assert_eq!(deck.get_daily_counts(today()).new, 1);
+        assert_eq!(deck.get_cards_to_review_indices(now(), None).len(), 1);
+
+        let tomorrow = now() + Duration::days(1);
+        assert_eq!(deck.get_daily_counts(tomorrow.date()).new, 0);
+        let counts = deck.get_study_counts(tomorrow, None);
+        assert_eq!((counts.new, counts.review), (2, 1));
+    }
+
+    #[test]
+    fn limits_reviews_per_day() {
+        let mut deck = deck_of(4, "0/2");
+        for i in 0..4 {
+            deck.review_card(i, 4, now() - Duration::days(5));
+        }
+        assert_eq!(deck.get_cards_to_review_indices(now(), None).len(), 2);
+
+        let first = deck.get_cards_to_review_indices(now(), None)[0];
+        deck.review_card(first, 4, now());
+        assert_eq!(deck.get_daily_counts(today()).reviews, 1);
+        assert_eq!(deck.get_cards_to_review_indices(now(), None).len(), 1);
+    }
+
+    #[test]
+    fn lapsed_cards_count_as_reviews() {
+        let mut deck = deck_of(1, "0/0");
+        deck.review_card(0, 4, now() - Duration::days(5));
+        deck.review_card(0, 1, now() - Duration::days(1));
+        assert!(deck.get_cards_to_review_indices(now(), None).is_empty());
+
+        deck.set_learning_steps("1m / 10m".parse().unwrap());
+        deck.review_card(0, 4, now() - Duration::days(1));
+        deck.review_card(0, 1, now() - Duration::days(1));
+        assert!(deck.cards[0].is_learning());
+        assert_eq!(deck.get_cards_to_review_indices(now(), None), vec![0]);
+    }
+}
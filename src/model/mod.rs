@@ -1,6 +1,39 @@
+mod cloze;
 mod collection;
 mod deck;
 mod flashcard;
+mod history;
+mod import;
+mod learning;
+mod limits;
+mod note;
+mod quiz;
+mod review_log;
+mod scheduler;
+mod source;
+mod stats;
+mod tags;
+#[cfg(test)]
+pub(crate) mod testing;
+mod tree;
+mod typing;
+mod update;
 
+pub use cloze::*;
 pub use collection::*;
 pub use deck::*;
+pub use flashcard::*;
+pub use history::*;
+pub use import::*;
+pub use learning::*;
+pub use limits::*;
+pub use note::*;
+pub use quiz::*;
+pub use review_log::*;
+pub use scheduler::*;
+pub use source::*;
+pub use stats::*;
+pub use tags::*;
+pub use tree::*;
+pub use typing::*;
+pub use update::*;
@@ -0,0 +1,216 @@
+use super::cloze::cloze_numbers;
+use super::deck::Deck;
+use super::flashcard::{FlashCard, FlashCardError};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CardDirection {
+    #[default]
+    Forward,
+    Reverse,
+    Both,
+}
+impl CardDirection {
+    pub fn next(self) -> CardDirection {
+        match self {
+            CardDirection::Forward => CardDirection::Both,
+            CardDirection::Both => CardDirection::Reverse,
+            CardDirection::Reverse => CardDirection::Forward,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            CardDirection::Forward => "pytanie → odpowiedź",
+            CardDirection::Reverse => "odpowiedź → pytanie",
+            CardDirection::Both => "w obie strony",
+        }
+    }
+    fn has_forward(self) -> bool {
+        self != CardDirection::Reverse
+    }
+    fn has_reverse(self) -> bool {
+        self != CardDirection::Forward
+    }
+}
+impl fmt::Display for CardDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CardDirection::Forward => "forward",
+            CardDirection::Reverse => "reverse",
+            CardDirection::Both => "both",
+        })
+    }
+}
+impl FromStr for CardDirection {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "forward" | "normalna" | "normalne" => Ok(CardDirection::Forward),
+            "reverse" | "reversed" | "odwrotna" | "odwrotne" => Ok(CardDirection::Reverse),
+            "both" | "obie" | "oba" => Ok(CardDirection::Both),
+            _ => Err(format!(
+                "nieznany kierunek kart: {} (dostępne: forward, reverse, both)",
+                s
+            )),
+        }
+    }
+}
+
+impl FlashCard {
+    pub fn new_note(
+        question: String,
+        answer: String,
+        direction: CardDirection,
+        today: NaiveDate,
+    ) -> Result<Vec<FlashCard>, FlashCardError> {
+        let numbers = cloze_numbers(&question);
+        if !numbers.is_empty() {
+            let note_id = (numbers.len() > 1).then(Uuid::new_v4);
+            return numbers
+                .into_iter()
+                .map(|number| {
+                    let mut card =
+                        FlashCard::new_cloze(question.clone(), answer.clone(), number, today)?;
+                    card.note_id = note_id;
+                    Ok(card)
+                })
+                .collect();
+        }
+        let note_id = (direction == CardDirection::Both).then(Uuid::new_v4);
+        let mut cards = vec![];
+        if direction.has_forward() {
+            let mut card = FlashCard::new(question.clone(), answer.clone(), today)?;
+            card.note_id = note_id;
+            cards.push(card);
+        }
+        if direction.has_reverse() {
+            let mut card = FlashCard::new(answer, question, today)?;
+            card.note_id = note_id;
+            card.reversed = true;
+            cards.push(card);
+        }
+        Ok(cards)
+    }
+    pub fn is_buried(&self, today: NaiveDate) -> bool {
+        self.buried_until.is_some_and(|date| today <= date)
+    }
+    pub fn get_note_id(&self) -> Uuid {
+        self.note_id.unwrap_or(self.id)
+    }
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+    pub fn get_note_text(&self) -> (String, String) {
+        if self.reversed {
+            (self.answer.clone(), self.question.clone())
+        } else {
+            (self.question.clone(), self.answer.clone())
+        }
+    }
+    pub(crate) fn set_note_text(&mut self, front: String, back: String) {
+        if self.reversed {
+            self.question = back;
+            self.answer = front;
+        } else {
+            self.question = front;
+            self.answer = back;
+        }
+    }
+}
+
+impl Deck {
+    pub fn get_siblings(&self, index: usize) -> Vec<usize> {
+        let Some(card) = self.cards.get(index) else {
+            return vec![];
+        };
+        let note_id = card.get_note_id();
+        (0..self.cards.len())
+            .filter(|&i| i != index && self.cards[i].get_note_id() == note_id)
+            .collect()
+    }
+    pub fn get_note_groups(&self) -> HashMap<Uuid, Vec<usize>> {
+        let mut notes: HashMap<Uuid, Vec<usize>> = HashMap::new();
+        for (i, card) in self.cards.iter().enumerate() {
+            notes.entry(card.get_note_id()).or_default().push(i);
+        }
+        notes
+    }
+    pub fn get_note_direction(&self, index: usize) -> CardDirection {
+        let mut note = self.get_siblings(index);
+        note.extend(self.cards.get(index).map(|_| index));
+        self.direction_of(&note)
+    }
+    pub(crate) fn direction_of(&self, note: &[usize]) -> CardDirection {
+        let forward = note.iter().any(|&i| !self.cards[i].reversed);
+        let reverse = note.iter().any(|&i| self.cards[i].reversed);
+        match (forward, reverse) {
+            (true, true) => CardDirection::Both,
+            (false, true) => CardDirection::Reverse,
+            _ => CardDirection::Forward,
+        }
+    }
+    pub(crate) fn sync_siblings(&mut self, index: usize) {
+        let Some(card) = self.cards.get(index) else {
+            return;
+        };
+        let (front, back) = card.get_note_text();
+        for sibling in self.get_siblings(index) {
+            self.cards[sibling].set_note_text(front.clone(), back.clone());
+        }
+    }
+    pub fn set_note_direction(
+        &mut self,
+        index: usize,
+        direction: CardDirection,
+        today: NaiveDate,
+    ) -> Result<(), FlashCardError> {
+        let Some(card) = self.cards.get(index).filter(|card| card.cloze.is_none()) else {
+            return Ok(());
+        };
+        let note_id = card.get_note_id();
+        let (front, back) = card.get_note_text();
+        let tags = card.tags.clone();
+        let mut has_forward = false;
+        let mut has_reverse = false;
+        self.cards.retain(|card| {
+            if card.get_note_id() != note_id {
+                return true;
+            }
+            if card.reversed {
+                has_reverse |= direction.has_reverse();
+                direction.has_reverse()
+            } else {
+                has_forward |= direction.has_forward();
+                direction.has_forward()
+            }
+        });
+        for (reversed, missing) in [
+            (false, direction.has_forward() && !has_forward),
+            (true, direction.has_reverse() && !has_reverse),
+        ] {
+            if missing {
+                let mut card = FlashCard::new(front.clone(), back.clone(), today)?;
+                card.note_id = Some(note_id);
+                card.reversed = reversed;
+                card.set_note_text(front.clone(), back.clone());
+                card.tags = tags.clone();
+                self.cards.push(card);
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn bury_siblings(&mut self, index: usize, today: NaiveDate) {
+        for sibling in self.get_siblings(index) {
+            let card = &mut self.cards[sibling];
+            if card.next_review_date <= today {
+                card.buried_until = Some(today);
+            }
+        }
+    }
+}
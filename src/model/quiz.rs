@@ -0,0 +1,228 @@
+use super::collection::Collection;
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+pub const QUIZ_CHOICES: usize = 4;
+const DISTRACTOR_POOL: usize = 6;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuizLogEntry {
+    pub id: Uuid,
+    pub card_id: Uuid,
+    pub timestamp: NaiveDateTime,
+    pub correct: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuizQuestion {
+    pub choices: Vec<String>,
+    pub correct: usize,
+}
+impl QuizQuestion {
+    pub fn is_correct(&self, choice: usize) -> bool {
+        choice == self.correct
+    }
+}
+
+pub struct Shuffler(u64);
+impl Default for Shuffler {
+    fn default() -> Shuffler {
+        Shuffler(Uuid::new_v4().as_u64_pair().0 | 1)
+    }
+}
+impl Shuffler {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.trim().to_lowercase()
+}
+
+fn is_number(text: &str) -> bool {
+    let text = text.trim();
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | ' '))
+}
+
+fn distance(card: &FlashCard, other: &FlashCard, answer: &str, candidate: &str) -> usize {
+    let length = answer.chars().count().abs_diff(candidate.chars().count()) * 10
+        / answer.chars().count().max(1);
+    let words = answer
+        .split_whitespace()
+        .count()
+        .abs_diff(candidate.split_whitespace().count());
+    let kind = (card.get_cloze().is_some() != other.get_cloze().is_some()) as usize
+        + (card.reversed != other.reversed) as usize
+        + (is_number(answer) != is_number(candidate)) as usize;
+    length + words * 2 + kind * 10
+}
+
+impl Deck {
+    pub fn quiz_question(&self, index: usize, shuffler: &mut Shuffler) -> Option<QuizQuestion> {
+        let card = self.cards.get(index)?;
+        let answer = card.get_expected_answer();
+        let mut seen = HashSet::from([normalize(&answer)]);
+        let note = card.get_note_id();
+        let mut candidates: Vec<(usize, String)> = vec![];
+        for other in &self.cards {
+            if other.get_note_id() == note {
+                continue;
+            }
+            let candidate = other.get_expected_answer();
+            if candidate.trim().is_empty() || !seen.insert(normalize(&candidate)) {
+                continue;
+            }
+            let score = distance(card, other, &answer, &candidate) * 100 + shuffler.below(100);
+            candidates.push((score, candidate));
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+        candidates.sort_by_key(|(score, _)| *score);
+        candidates.truncate(DISTRACTOR_POOL);
+        shuffler.shuffle(&mut candidates);
+        let mut choices: Vec<String> = candidates
+            .into_iter()
+            .take(QUIZ_CHOICES - 1)
+            .map(|(_, candidate)| candidate)
+            .collect();
+        let correct = shuffler.below(choices.len() + 1);
+        choices.insert(correct, answer);
+        Some(QuizQuestion { choices, correct })
+    }
+}
+
+impl Collection {
+    pub fn record_quiz_answer(
+        &mut self,
+        card_id: Uuid,
+        correct: bool,
+        now: NaiveDateTime,
+    ) -> &QuizLogEntry {
+        self.quiz_log.push(QuizLogEntry {
+            id: Uuid::new_v4(),
+            card_id,
+            timestamp: now,
+            correct,
+        });
+        &self.quiz_log[self.quiz_log.len() - 1]
+    }
+    pub fn get_quiz_log(&self) -> &[QuizLogEntry] {
+        &self.quiz_log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::note::CardDirection;
+    use crate::model::testing::{card, deck, today};
+
+    fn deck_with_answers(answers: &[&str]) -> Deck {
+        let mut deck = deck(&[]);
+        for (i, answer) in answers.iter().enumerate() {
+            deck.add_card(card(&format!("pytanie {}", i), answer));
+        }
+        deck
+    }
+
+    #[test]
+    fn shuffler_is_deterministic_and_bounded() {
+        let (mut a, mut b) = (Shuffler(42), Shuffler(42));
+        for n in 1..50 {
+            let value = a.below(n);
+            assert_eq!(value, b.below(n));
+            assert!(value < n);
+        }
+        assert_eq!(a.below(0), 0);
+    }
+
+    #[test]
+    fn shuffle_permutes_items() {
+        let mut shuffler = Shuffler(7);
+        let mut orders = HashSet::new();
+        for _ in 0..20 {
+            let mut items: Vec<u32> = (0..6).collect();
+            shuffler.shuffle(&mut items);
+            orders.insert(items.clone());
+            items.sort();
+            assert_eq!(items, (0..6).collect::<Vec<_>>());
+        }
+        assert!(orders.len() > 1);
+    }
+
+    #[test]
+    fn question_contains_answer_and_unique_distractors() {
+        let deck = deck_with_answers(&["kot", "pies", "Pies", "koń", "mysz", "ryba", "kot "]);
+        for seed in 1..30 {
+            let question = deck.quiz_question(0, &mut Shuffler(seed)).unwrap();
+            assert_eq!(question.choices.len(), QUIZ_CHOICES);
+            assert_eq!(question.choices[question.correct], "kot");
+            assert!(question.is_correct(question.correct));
+            let unique: HashSet<String> = question.choices.iter().map(|c| normalize(c)).collect();
+            assert_eq!(unique.len(), QUIZ_CHOICES);
+        }
+    }
+
+    #[test]
+    fn prefers_similar_distractors() {
+        let deck = deck_with_answers(&[
+            "1918",
+            "1410",
+            "1569",
+            "1791",
+            "1989",
+            "1683",
+            "1945",
+            "Mikołaj Kopernik",
+            "Maria Skłodowska-Curie",
+            "Fryderyk Chopin",
+        ]);
+        for seed in 1..30 {
+            let question = deck.quiz_question(0, &mut Shuffler(seed)).unwrap();
+            assert!(question.choices.iter().all(|choice| is_number(choice)));
+        }
+    }
+
+    #[test]
+    fn skips_cards_from_the_same_note() {
+        let mut deck = deck(&[]);
+        for card in
+            FlashCard::new_note("pies".into(), "dog".into(), CardDirection::Both, today()).unwrap()
+        {
+            deck.add_card(card);
+        }
+        assert!(deck.quiz_question(0, &mut Shuffler(3)).is_none());
+
+        deck.add_card(card("kot", "cat"));
+        let question = deck.quiz_question(0, &mut Shuffler(3)).unwrap();
+        assert_eq!(question.choices.len(), 2);
+        assert!(!question.choices.contains(&"pies".to_string()));
+    }
+
+    #[test]
+    fn no_question_without_distractors() {
+        let deck = deck_with_answers(&["kot", "Kot"]);
+        assert!(deck.quiz_question(0, &mut Shuffler(5)).is_none());
+        assert!(deck.quiz_question(2, &mut Shuffler(5)).is_none());
+    }
+}
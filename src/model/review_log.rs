@@ -0,0 +1,34 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReviewLogEntry {
+    pub id: Uuid,
+    pub card_id: Uuid,
+    pub timestamp: NaiveDateTime,
+    pub rating: u32,
+    pub previous_interval: u32,
+    pub new_interval: u32,
+    pub previous_ease: f32,
+    pub new_ease: f32,
+    pub time_spent_ms: u64,
+    #[serde(default)]
+    pub undoes: Option<Uuid>,
+}
+impl ReviewLogEntry {
+    pub fn undo(&self, now: NaiveDateTime) -> ReviewLogEntry {
+        ReviewLogEntry {
+            id: Uuid::new_v4(),
+            timestamp: now,
+            undoes: Some(self.id),
+            ..self.clone()
+        }
+    }
+    pub fn redo(&self) -> ReviewLogEntry {
+        ReviewLogEntry {
+            id: Uuid::new_v4(),
+            ..self.clone()
+        }
+    }
+}
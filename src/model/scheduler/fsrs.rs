@@ -0,0 +1,115 @@
+use super::Scheduler;
+use crate::model::flashcard::FlashCard;
+use chrono::{Duration, NaiveDate};
+
+const DECAY: f32 = -0.5;
+const FACTOR: f32 = 19.0 / 81.0;
+const MAX_INTERVAL: f32 = 36500.0;
+const DEFAULT_WEIGHTS: [f32; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072,
+    0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+pub struct Fsrs {
+    pub weights: [f32; 17],
+    pub desired_retention: f32,
+}
+impl Default for Fsrs {
+    fn default() -> Self {
+        Fsrs {
+            weights: DEFAULT_WEIGHTS,
+            desired_retention: 0.9,
+        }
+    }
+}
+impl Fsrs {
+    fn grade(rating: u32) -> u32 {
+        match rating {
+            0..=2 => 1,
+            3 => 2,
+            4 => 3,
+            _ => 4,
+        }
+    }
+    pub fn retrievability(&self, elapsed_days: f32, stability: f32) -> f32 {
+        (1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+    }
+    fn next_interval(&self, stability: f32) -> u32 {
+        let interval = stability / FACTOR * (self.desired_retention.powf(1.0 / DECAY) - 1.0);
+        interval.round().clamp(1.0, MAX_INTERVAL) as u32
+    }
+    fn initial_stability(&self, grade: u32) -> f32 {
+        self.weights[grade as usize - 1].max(0.1)
+    }
+    fn initial_difficulty(&self, grade: u32) -> f32 {
+        (self.weights[4] - (grade as f32 - 3.0) * self.weights[5]).clamp(1.0, 10.0)
+    }
+    fn next_difficulty(&self, difficulty: f32, grade: u32) -> f32 {
+        let next = difficulty - self.weights[6] * (grade as f32 - 3.0);
+        let reverted =
+            self.weights[7] * self.initial_difficulty(3) + (1.0 - self.weights[7]) * next;
+        reverted.clamp(1.0, 10.0)
+    }
+    fn recall_stability(&self, difficulty: f32, stability: f32, r: f32, grade: u32) -> f32 {
+        let w = &self.weights;
+        let hard_penalty = if grade == 2 { w[15] } else { 1.0 };
+        let easy_bonus = if grade == 4 { w[16] } else { 1.0 };
+        stability
+            * (w[8].exp()
+                * (11.0 - difficulty)
+                * stability.powf(-w[9])
+                * ((w[10] * (1.0 - r)).exp() - 1.0)
+                * hard_penalty
+                * easy_bonus
+                + 1.0)
+    }
+    fn forget_stability(&self, difficulty: f32, stability: f32, r: f32) -> f32 {
+        let w = &self.weights;
+        let next = w[11]
+            * difficulty.powf(-w[12])
+            * ((stability + 1.0).powf(w[13]) - 1.0)
+            * (w[14] * (1.0 - r)).exp();
+        next.min(stability)
+    }
+    pub fn difficulty_from_ef(ef: f32) -> f32 {
+        (5.0 - (ef - 2.5) / 0.24).clamp(1.0, 10.0)
+    }
+}
+
+impl Scheduler for Fsrs {
+    fn review(&self, card: &mut FlashCard, rating: u32, today: NaiveDate) {
+        let grade = Fsrs::grade(rating);
+        if card.stability <= 0.0 {
+            card.stability = self.initial_stability(grade);
+            card.difficulty = self.initial_difficulty(grade);
+        } else {
+            let elapsed = (today - card.last_review_date).num_days().max(0) as f32;
+            let r = self.retrievability(elapsed, card.stability);
+            card.stability = if grade == 1 {
+                self.forget_stability(card.difficulty, card.stability, r)
+            } else {
+                self.recall_stability(card.difficulty, card.stability, r, grade)
+            };
+            card.difficulty = self.next_difficulty(card.difficulty, grade);
+        }
+        if grade == 1 {
+            card.repetitions = 0;
+            card.lapses += 1;
+        } else {
+            card.repetitions += 1;
+        }
+        card.interval = self.next_interval(card.stability);
+        card.ef = (2.5 - (card.difficulty - 5.0) * 0.24).max(1.3);
+        card.last_review_date = today;
+        card.next_review_date = today + Duration::days(card.interval as i64);
+    }
+    fn migrate(&self, card: &mut FlashCard) {
+        if card.is_new() {
+            card.stability = 0.0;
+            card.difficulty = 0.0;
+            return;
+        }
+        card.stability = (card.interval as f32).max(0.1);
+        card.difficulty = Fsrs::difficulty_from_ef(card.ef);
+    }
+}
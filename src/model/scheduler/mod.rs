@@ -0,0 +1,136 @@
+mod fsrs;
+mod sm2;
+
+use crate::model::flashcard::FlashCard;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+pub use fsrs::Fsrs;
+pub use sm2::Sm2;
+
+pub trait Scheduler {
+    fn review(&self, card: &mut FlashCard, rating: u32, today: NaiveDate);
+    fn migrate(&self, card: &mut FlashCard);
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedulerKind {
+    #[default]
+    Sm2,
+    Fsrs,
+}
+impl SchedulerKind {
+    pub fn scheduler(self) -> Box<dyn Scheduler> {
+        match self {
+            SchedulerKind::Sm2 => Box::new(Sm2),
+            SchedulerKind::Fsrs => Box::new(Fsrs::default()),
+        }
+    }
+    pub fn next(self) -> SchedulerKind {
+        match self {
+            SchedulerKind::Sm2 => SchedulerKind::Fsrs,
+            SchedulerKind::Fsrs => SchedulerKind::Sm2,
+        }
+    }
+}
+impl fmt::Display for SchedulerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerKind::Sm2 => write!(f, "SM-2"),
+            SchedulerKind::Fsrs => write!(f, "FSRS"),
+        }
+    }
+}
+impl FromStr for SchedulerKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SM-2" => Ok(SchedulerKind::Sm2),
+            "FSRS" => Ok(SchedulerKind::Fsrs),
+            other => Err(format!("Nieznany algorytm powtórek: {}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::{card, deck, now, today};
+    use chrono::Duration;
+
+    #[test]
+    fn sm2_grows_interval_and_resets_on_lapse() {
+        let mut card = card("pytanie", "odpowiedź");
+        for expected in [1, 6, 15] {
+            Sm2.review(&mut card, 4, today());
+            assert_eq!(card.interval, expected);
+        }
+        assert_eq!(card.repetitions, 3);
+        assert_eq!(card.next_review_date, today() + Duration::days(15));
+
+        Sm2.review(&mut card, 1, today());
+        assert_eq!((card.interval, card.repetitions, card.lapses), (1, 0, 1));
+        assert!((card.ef - 2.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sm2_keeps_ease_above_minimum() {
+        let mut card = card("pytanie", "odpowiedź");
+        for _ in 0..20 {
+            Sm2.review(&mut card, 3, today());
+        }
+        assert_eq!(card.ef, 1.3);
+        Sm2.review(&mut card, 5, today());
+        assert!(card.ef > 1.3);
+    }
+
+    #[test]
+    fn fsrs_starts_from_initial_weights() {
+        let fsrs = Fsrs::default();
+        let mut card = card("pytanie", "odpowiedź");
+        fsrs.review(&mut card, 4, today());
+        assert_eq!(card.stability, fsrs.weights[2]);
+        assert_eq!(card.difficulty, fsrs.weights[4]);
+        assert_eq!(card.interval, 4);
+        assert_eq!(card.next_review_date, today() + Duration::days(4));
+    }
+
+    #[test]
+    fn fsrs_lapse_lowers_stability() {
+        let fsrs = Fsrs::default();
+        let mut card = card("pytanie", "odpowiedź");
+        fsrs.review(&mut card, 4, today());
+        let initial = card.stability;
+        let due = card.next_review_date;
+        fsrs.review(&mut card, 4, due);
+        assert!(card.stability > initial);
+
+        let (grown, interval) = (card.stability, card.interval);
+        let due = card.next_review_date;
+        fsrs.review(&mut card, 1, due);
+        assert!(card.stability < grown);
+        assert!(card.interval < interval);
+        assert_eq!((card.repetitions, card.lapses), (0, 1));
+    }
+
+    #[test]
+    fn switching_scheduler_migrates_cards() {
+        let mut deck = deck(&[("dom", "house"), ("kot", "cat")]);
+        deck.set_learning_steps(" / ".parse().unwrap());
+        deck.review_card(0, 4, now());
+        deck.review_card(0, 4, now());
+
+        deck.set_scheduler(SchedulerKind::Fsrs);
+        let reviewed = deck.get_card(0).unwrap();
+        assert_eq!(reviewed.stability, 6.0);
+        assert!((reviewed.difficulty - 5.0).abs() < 1e-4);
+        let new = deck.get_card(1).unwrap();
+        assert_eq!((new.stability, new.difficulty), (0.0, 0.0));
+
+        deck.set_scheduler(SchedulerKind::Sm2);
+        assert!((deck.get_card(0).unwrap().ef - 2.5).abs() < 1e-4);
+        assert_eq!(deck.get_card(1).unwrap().ef, 2.5);
+    }
+}
@@ -0,0 +1,35 @@
+use super::Scheduler;
+use crate::model::flashcard::FlashCard;
+use chrono::{Duration, NaiveDate};
+
+const MIN_EF: f32 = 1.3;
+
+pub struct Sm2;
+
+impl Scheduler for Sm2 {
+    fn review(&self, card: &mut FlashCard, rating: u32, today: NaiveDate) {
+        let q = rating as f32;
+        let mut new_ef = card.ef + (0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02));
+        new_ef = new_ef.max(MIN_EF);
+        if rating < 3 {
+            card.repetitions = 0;
+            card.interval = 1;
+            card.lapses += 1;
+        } else {
+            card.interval = match card.interval {
+                0 => 1,
+                1 => 6,
+                _ => (card.interval as f32 * card.ef).round() as u32,
+            };
+            card.ef = new_ef;
+            card.repetitions += 1;
+        }
+        card.last_review_date = today;
+        card.next_review_date = today + Duration::days(card.interval as i64);
+    }
+    fn migrate(&self, card: &mut FlashCard) {
+        if card.difficulty > 0.0 {
+            card.ef = (2.5 - (card.difficulty - 5.0) * 0.24).max(MIN_EF);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SourceFormat {
+    Json,
+    Csv,
+    Anki,
+}
+impl fmt::Display for SourceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SourceFormat::Json => "JSON",
+            SourceFormat::Csv => "CSV",
+            SourceFormat::Anki => "Anki",
+        })
+    }
+}
+impl FromStr for SourceFormat {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "JSON" => Ok(SourceFormat::Json),
+            "CSV" => Ok(SourceFormat::Csv),
+            "Anki" => Ok(SourceFormat::Anki),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeckSource {
+    pub path: PathBuf,
+    pub format: SourceFormat,
+    #[serde(default)]
+    pub options: String,
+    pub imported_at: NaiveDateTime,
+    #[serde(default)]
+    pub modified_at: Option<NaiveDateTime>,
+}
+impl DeckSource {
+    pub fn new(path: &Path, format: SourceFormat, options: String) -> DeckSource {
+        DeckSource {
+            path: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+            format,
+            options,
+            imported_at: Local::now().naive_local(),
+            modified_at: file_modified(path),
+        }
+    }
+    pub fn current_modified(&self) -> Option<NaiveDateTime> {
+        file_modified(&self.path)
+    }
+    pub fn has_changed(&self) -> bool {
+        match (self.current_modified(), self.modified_at) {
+            (Some(current), Some(known)) => current > known,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+fn file_modified(path: &Path) -> Option<NaiveDateTime> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Local>::from(modified).naive_local())
+}
@@ -0,0 +1,109 @@
+use crate::model::flashcard::FlashCard;
+use crate::model::quiz::QuizLogEntry;
+use crate::model::review_log::ReviewLogEntry;
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+pub const HISTORY_DAYS: i64 = 30;
+pub const FORECAST_DAYS: i64 = 30;
+pub const HEATMAP_WEEKS: i64 = 26;
+const INTERVAL_BUCKETS: [(u32, &str); 7] = [
+    (1, "≤1d"),
+    (3, "2-3d"),
+    (7, "4-7d"),
+    (14, "1-2t"),
+    (30, "2-4t"),
+    (90, "1-3m"),
+    (u32::MAX, ">3m"),
+];
+const EASE_BUCKETS: [(f32, &str); 6] = [
+    (1.5, "<1.5"),
+    (1.9, "1.5+"),
+    (2.3, "1.9+"),
+    (2.5, "2.3+"),
+    (2.8, "2.5+"),
+    (f32::MAX, "2.8+"),
+];
+
+pub struct Statistics {
+    pub card_count: usize,
+    pub new_count: usize,
+    pub total_reviews: usize,
+    pub retention: Option<f64>,
+    pub reviews_per_day: Vec<u64>,
+    pub forecast: Vec<u64>,
+    pub interval_histogram: Vec<(&'static str, u64)>,
+    pub ease_distribution: Vec<(&'static str, u64)>,
+    pub activity: BTreeMap<NaiveDate, u64>,
+    pub quiz_answers: usize,
+    pub quiz_correct: usize,
+}
+impl Statistics {
+    pub fn add_quiz_answers<'a>(&mut self, log: impl Iterator<Item = &'a QuizLogEntry>) {
+        for entry in log {
+            self.quiz_answers += 1;
+            self.quiz_correct += entry.correct as usize;
+        }
+    }
+    pub fn compute<'a>(
+        cards: impl Iterator<Item = &'a FlashCard>,
+        log: impl Iterator<Item = &'a ReviewLogEntry>,
+        today: NaiveDate,
+    ) -> Statistics {
+        let mut stats = Statistics {
+            card_count: 0,
+            new_count: 0,
+            total_reviews: 0,
+            retention: None,
+            reviews_per_day: vec![0; HISTORY_DAYS as usize],
+            forecast: vec![0; FORECAST_DAYS as usize],
+            interval_histogram: INTERVAL_BUCKETS.iter().map(|(_, l)| (*l, 0)).collect(),
+            ease_distribution: EASE_BUCKETS.iter().map(|(_, l)| (*l, 0)).collect(),
+            activity: BTreeMap::new(),
+            quiz_answers: 0,
+            quiz_correct: 0,
+        };
+
+        for card in cards {
+            stats.card_count += 1;
+            if card.is_new() {
+                stats.new_count += 1;
+                continue;
+            }
+            let days_until = (card.get_date() - today).num_days().max(0);
+            if days_until < FORECAST_DAYS {
+                stats.forecast[days_until as usize] += 1;
+            }
+            if let Some(bucket) = INTERVAL_BUCKETS
+                .iter()
+                .position(|(limit, _)| card.get_interval() <= *limit)
+            {
+                stats.interval_histogram[bucket].1 += 1;
+            }
+            if let Some(bucket) = EASE_BUCKETS
+                .iter()
+                .position(|(limit, _)| card.get_ef() < *limit)
+            {
+                stats.ease_distribution[bucket].1 += 1;
+            }
+        }
+
+        let history_start = today - Duration::days(HISTORY_DAYS - 1);
+        let mut passed = 0;
+        for entry in log {
+            stats.total_reviews += 1;
+            if entry.rating >= 3 {
+                passed += 1;
+            }
+            let date = entry.timestamp.date();
+            *stats.activity.entry(date).or_insert(0) += 1;
+            if date >= history_start && date <= today {
+                stats.reviews_per_day[(date - history_start).num_days() as usize] += 1;
+            }
+        }
+        if stats.total_reviews > 0 {
+            stats.retention = Some(passed as f64 / stats.total_reviews as f64);
+        }
+        stats
+    }
+}
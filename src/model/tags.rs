@@ -0,0 +1,318 @@
+use super::collection::Collection;
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use chrono::NaiveDateTime;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::vec::IntoIter;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TagExprError {
+    #[error("Wyrażenie z tagami jest puste.")]
+    Empty,
+    #[error("Brakuje tagu po \"{0}\".")]
+    MissingOperand(String),
+    #[error("Niedomknięty nawias w wyrażeniu z tagami.")]
+    UnclosedParen,
+    #[error("Nieoczekiwany symbol \"{0}\" w wyrażeniu z tagami.")]
+    Unexpected(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagExpr {
+    Tag(String),
+    Not(Box<TagExpr>),
+    And(Box<TagExpr>, Box<TagExpr>),
+    Or(Box<TagExpr>, Box<TagExpr>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+impl Token {
+    fn starts_operand(&self) -> bool {
+        matches!(self, Token::Tag(_) | Token::Not | Token::Open)
+    }
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Tag(tag) => f.write_str(tag),
+            Token::And => f.write_str("and"),
+            Token::Or => f.write_str("or"),
+            Token::Not => f.write_str("not"),
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    for word in text
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+    {
+        let token = match word.to_lowercase().as_str() {
+            "and" | "i" | "&&" => Token::And,
+            "or" | "lub" | "||" => Token::Or,
+            "not" | "nie" | "!" => Token::Not,
+            "(" => Token::Open,
+            ")" => Token::Close,
+            _ => match word.strip_prefix(['-', '!']) {
+                Some(tag) if !tag.is_empty() => {
+                    tokens.push(Token::Not);
+                    Token::Tag(tag.to_string())
+                }
+                _ => Token::Tag(word.to_string()),
+            },
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+}
+impl Parser {
+    fn or(&mut self) -> Result<TagExpr, TagExprError> {
+        let mut expr = self.and()?;
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            if !self.tokens.peek().is_some_and(Token::starts_operand) {
+                return Err(TagExprError::MissingOperand(Token::Or.to_string()));
+            }
+            expr = TagExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+    fn and(&mut self) -> Result<TagExpr, TagExprError> {
+        let mut expr = self.unary()?;
+        loop {
+            if self.tokens.next_if_eq(&Token::And).is_some() {
+                expr = TagExpr::And(Box::new(expr), Box::new(self.operand(Token::And)?));
+            } else if self.tokens.peek().is_some_and(Token::starts_operand) {
+                expr = TagExpr::And(Box::new(expr), Box::new(self.unary()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+    fn operand(&mut self, after: Token) -> Result<TagExpr, TagExprError> {
+        if self.tokens.peek().is_some_and(Token::starts_operand) {
+            self.unary()
+        } else {
+            Err(TagExprError::MissingOperand(after.to_string()))
+        }
+    }
+    fn unary(&mut self) -> Result<TagExpr, TagExprError> {
+        match self.tokens.next() {
+            Some(Token::Not) => Ok(TagExpr::Not(Box::new(self.operand(Token::Not)?))),
+            Some(Token::Tag(tag)) => Ok(TagExpr::Tag(tag)),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(TagExprError::UnclosedParen),
+                }
+            }
+            Some(token) => Err(TagExprError::Unexpected(token.to_string())),
+            None => Err(TagExprError::Empty),
+        }
+    }
+}
+
+impl FromStr for TagExpr {
+    type Err = TagExprError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s).into_iter().peekable(),
+        };
+        let expr = parser.or()?;
+        match parser.tokens.next() {
+            Some(token) => Err(TagExprError::Unexpected(token.to_string())),
+            None => Ok(expr),
+        }
+    }
+}
+impl TagExpr {
+    pub fn matches(&self, card: &FlashCard) -> bool {
+        match self {
+            TagExpr::Tag(tag) => card.has_tag(tag),
+            TagExpr::Not(expr) => !expr.matches(card),
+            TagExpr::And(left, right) => left.matches(card) && right.matches(card),
+            TagExpr::Or(left, right) => left.matches(card) || right.matches(card),
+        }
+    }
+}
+
+pub struct TagCount {
+    pub tag: String,
+    pub cards: usize,
+    pub due: usize,
+}
+
+impl FlashCard {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.to_lowercase();
+        self.tags.iter().any(|t| {
+            let t = t.to_lowercase();
+            t == tag
+                || t.strip_prefix(&tag)
+                    .is_some_and(|rest| rest.starts_with("::"))
+        })
+    }
+}
+
+impl Deck {
+    pub fn get_card_count_matching(&self, filter: Option<&TagExpr>) -> usize {
+        self.cards
+            .iter()
+            .filter(|card| filter.is_none_or(|f| f.matches(card)))
+            .count()
+    }
+    pub(crate) fn set_note_tags(&mut self, index: usize, tags: &str) {
+        if index >= self.cards.len() {
+            return;
+        }
+        let mut note = self.get_siblings(index);
+        note.push(index);
+        for i in note {
+            self.cards[i].set_tags(tags.split([' ', ',', ';']));
+        }
+    }
+}
+
+impl Collection {
+    pub fn tag_counts(&self, now: NaiveDateTime) -> Vec<TagCount> {
+        let mut counts: BTreeMap<String, TagCount> = BTreeMap::new();
+        for deck in &self.decks {
+            let due: HashSet<usize> = deck
+                .get_cards_to_review_indices(now, None)
+                .into_iter()
+                .collect();
+            for (i, card) in deck.cards.iter().enumerate() {
+                for tag in &card.tags {
+                    let count = counts
+                        .entry(tag.to_lowercase())
+                        .or_insert_with(|| TagCount {
+                            tag: tag.clone(),
+                            cards: 0,
+                            due: 0,
+                        });
+                    count.cards += 1;
+                    count.due += due.contains(&i) as usize;
+                }
+            }
+        }
+        counts.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::card;
+
+    fn tag(name: &str) -> Box<TagExpr> {
+        Box::new(TagExpr::Tag(name.to_string()))
+    }
+
+    fn card_with_tags(tags: &[&str]) -> FlashCard {
+        let mut card = card("pytanie", "odpowiedź");
+        card.set_tags(tags.iter().copied());
+        card
+    }
+
+    fn parse(text: &str) -> Result<TagExpr, TagExprError> {
+        text.parse()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parse("a or b and c").unwrap(),
+            TagExpr::Or(tag("a"), Box::new(TagExpr::And(tag("b"), tag("c"))))
+        );
+        assert_eq!(
+            parse("a b lub c").unwrap(),
+            TagExpr::Or(Box::new(TagExpr::And(tag("a"), tag("b"))), tag("c"))
+        );
+    }
+
+    #[test]
+    fn parses_negation_and_parentheses() {
+        assert_eq!(
+            parse("(a || b) && -c").unwrap(),
+            TagExpr::And(
+                Box::new(TagExpr::Or(tag("a"), tag("b"))),
+                Box::new(TagExpr::Not(tag("c")))
+            )
+        );
+        assert_eq!(
+            parse("nie a i !b").unwrap(),
+            TagExpr::And(
+                Box::new(TagExpr::Not(tag("a"))),
+                Box::new(TagExpr::Not(tag("b")))
+            )
+        );
+        assert_eq!(parse("-").unwrap(), TagExpr::Tag("-".to_string()));
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert_eq!(parse("   "), Err(TagExprError::Empty));
+        assert_eq!(parse("()"), Err(TagExprError::Unexpected(")".into())));
+        assert_eq!(
+            parse("a or"),
+            Err(TagExprError::MissingOperand("or".into()))
+        );
+        assert_eq!(
+            parse("a and or b"),
+            Err(TagExprError::MissingOperand("and".into()))
+        );
+        assert_eq!(
+            parse("a not"),
+            Err(TagExprError::MissingOperand("not".into()))
+        );
+        assert_eq!(parse("(a or b"), Err(TagExprError::UnclosedParen));
+        assert_eq!(parse("a )"), Err(TagExprError::Unexpected(")".into())));
+    }
+
+    #[test]
+    fn matches_hierarchical_tags_ignoring_case() {
+        let card = card_with_tags(&["Grammar::Nouns", "b1"]);
+        assert!(card.has_tag("grammar"));
+        assert!(card.has_tag("GRAMMAR::nouns"));
+        assert!(!card.has_tag("gram"));
+        assert!(!card.has_tag("nouns"));
+        assert!(!card.has_tag("grammar::nouns::plural"));
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let nouns = card_with_tags(&["grammar::nouns", "b1"]);
+        let verbs = card_with_tags(&["grammar::verbs"]);
+        let untagged = card_with_tags(&[]);
+
+        let expr = parse("grammar and not grammar::verbs").unwrap();
+        assert!(expr.matches(&nouns));
+        assert!(!expr.matches(&verbs));
+        assert!(!expr.matches(&untagged));
+
+        let expr = parse("-grammar or (b1 grammar::nouns)").unwrap();
+        assert!(expr.matches(&nouns));
+        assert!(!expr.matches(&verbs));
+        assert!(expr.matches(&untagged));
+    }
+}
@@ -0,0 +1,63 @@
+use super::collection::Collection;
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use super::scheduler::SchedulerKind;
+use chrono::{NaiveDate, NaiveDateTime};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+pub(crate) fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+}
+
+pub(crate) fn now() -> NaiveDateTime {
+    today().and_hms_opt(12, 0, 0).unwrap()
+}
+
+pub(crate) fn card(question: &str, answer: &str) -> FlashCard {
+    FlashCard::new(question.to_string(), answer.to_string(), today()).unwrap()
+}
+
+pub(crate) fn deck(cards: &[(&str, &str)]) -> Deck {
+    let mut deck = Deck::new("Test".to_string());
+    for (question, answer) in cards {
+        deck.add_card(card(question, answer));
+    }
+    deck
+}
+
+pub(crate) fn collection() -> Collection {
+    let mut collection = Collection::default();
+    collection.add_deck(deck(&[("dom", "house"), ("kot", "cat")]));
+    let mut fsrs = deck(&[("pies", "dog")]);
+    fsrs.set_scheduler(SchedulerKind::Fsrs);
+    collection.add_deck(fsrs);
+    collection.review_card(0, 0, 4, now(), Duration::from_secs(3));
+    collection.review_card(
+        1,
+        0,
+        2,
+        now() + chrono::Duration::minutes(1),
+        Duration::ZERO,
+    );
+    collection
+}
+
+pub(crate) struct TempDir(PathBuf);
+impl TempDir {
+    pub(crate) fn new() -> TempDir {
+        let path = std::env::temp_dir().join(format!("flashcards-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,296 @@
+use super::collection::{Collection, CollectionError};
+use std::collections::HashMap;
+
+pub const DECK_SEPARATOR: &str = "::";
+
+pub struct DeckTreeNode {
+    pub path: String,
+    pub name: String,
+    pub depth: usize,
+    pub deck_index: Option<usize>,
+    pub decks: Vec<usize>,
+    pub has_children: bool,
+}
+
+struct Node {
+    path: String,
+    deck_index: Option<usize>,
+    children: Vec<Node>,
+}
+
+pub fn normalize_deck_name(name: &str) -> String {
+    name.split(DECK_SEPARATOR)
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join(DECK_SEPARATOR)
+}
+
+pub fn parent_path(path: &str) -> Option<&str> {
+    path.rsplit_once(DECK_SEPARATOR).map(|(parent, _)| parent)
+}
+
+fn prefixes(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices(DECK_SEPARATOR)
+        .map(|(i, _)| &path[..i])
+        .chain(std::iter::once(path))
+}
+
+fn is_within(path: &str, ancestor: &str) -> bool {
+    path == ancestor
+        || path
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with(DECK_SEPARATOR))
+}
+
+fn insert(nodes: &mut Vec<Node>, path: &str, deck_index: usize) {
+    let mut level = nodes;
+    for prefix in prefixes(path) {
+        let is_last = prefix.len() == path.len();
+        let existing = level
+            .iter()
+            .position(|node| node.path == prefix && !(is_last && node.deck_index.is_some()));
+        let position = existing.unwrap_or_else(|| {
+            level.push(Node {
+                path: prefix.to_string(),
+                deck_index: None,
+                children: vec![],
+            });
+            level.len() - 1
+        });
+        if is_last {
+            level[position].deck_index = Some(deck_index);
+            return;
+        }
+        level = &mut level[position].children;
+    }
+}
+
+impl Collection {
+    pub fn deck_tree(&self) -> Vec<DeckTreeNode> {
+        let mut roots = vec![];
+        for (i, deck) in self.decks.iter().enumerate() {
+            let path = normalize_deck_name(&deck.name);
+            if !path.is_empty() {
+                insert(&mut roots, &path, i);
+            }
+        }
+        let mut rows = vec![];
+        let mut stack: Vec<(Node, usize)> = roots.into_iter().rev().map(|node| (node, 0)).collect();
+        while let Some((node, depth)) = stack.pop() {
+            let decks = match node.deck_index {
+                Some(i) if node.children.is_empty() => vec![i],
+                _ => self.subtree_decks(&node.path),
+            };
+            rows.push(DeckTreeNode {
+                name: node
+                    .path
+                    .rsplit(DECK_SEPARATOR)
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                path: node.path,
+                depth,
+                deck_index: node.deck_index,
+                decks,
+                has_children: !node.children.is_empty(),
+            });
+            stack.extend(
+                node.children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, depth + 1)),
+            );
+        }
+        rows
+    }
+    pub fn subtree_decks(&self, path: &str) -> Vec<usize> {
+        (0..self.decks.len())
+            .filter(|&i| is_within(&normalize_deck_name(&self.decks[i].name), path))
+            .collect()
+    }
+    pub fn deck_with_descendants(&self, index: usize) -> Vec<usize> {
+        let Some(deck) = self.decks.get(index) else {
+            return vec![];
+        };
+        let path = normalize_deck_name(&deck.name);
+        self.subtree_decks(&path)
+            .into_iter()
+            .filter(|&i| i == index || normalize_deck_name(&self.decks[i].name) != path)
+            .collect()
+    }
+    pub fn rename_deck(&mut self, index: usize, name: &str) -> Result<(), CollectionError> {
+        let name = normalize_deck_name(name);
+        if name.is_empty() {
+            return Err(CollectionError::EmptyDeckName);
+        }
+        let Some(deck) = self.decks.get(index) else {
+            return Ok(());
+        };
+        let old = normalize_deck_name(&deck.name);
+        if name != old && is_within(&name, &old) {
+            return Err(CollectionError::MoveIntoDescendant);
+        }
+        for i in self.deck_with_descendants(index) {
+            let path = normalize_deck_name(&self.decks[i].name);
+            self.decks[i].set_name(format!("{}{}", name, &path[old.len()..]));
+        }
+        Ok(())
+    }
+    pub fn move_deck(&mut self, path: &str, up: bool) -> bool {
+        let tree = self.deck_tree();
+        let siblings: Vec<&str> = tree
+            .iter()
+            .filter(|node| parent_path(&node.path) == parent_path(path))
+            .map(|node| node.path.as_str())
+            .collect();
+        let Some(position) = siblings.iter().position(|sibling| *sibling == path) else {
+            return false;
+        };
+        let target = if up {
+            position.checked_sub(1)
+        } else {
+            Some(position + 1)
+        };
+        let Some(other) = target.and_then(|t| siblings.get(t)).map(|s| s.to_string()) else {
+            return false;
+        };
+        let order: HashMap<&str, usize> = tree
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, node)| (node.path.as_str(), i))
+            .collect();
+        let rank = |prefix: &str| {
+            let prefix = match prefix {
+                p if p == path => other.as_str(),
+                p if p == other => path,
+                p => p,
+            };
+            order.get(prefix).copied().unwrap_or(usize::MAX)
+        };
+        let mut keys: Vec<(Vec<usize>, usize)> = self
+            .decks
+            .iter()
+            .enumerate()
+            .map(|(i, deck)| {
+                let name = normalize_deck_name(&deck.name);
+                (prefixes(&name).map(rank).collect(), i)
+            })
+            .collect();
+        keys.sort();
+        let mut decks: Vec<Option<_>> = self.decks.drain(..).map(Some).collect();
+        self.decks = keys
+            .into_iter()
+            .filter_map(|(_, i)| decks[i].take())
+            .collect();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Deck;
+
+    fn collection(names: &[&str]) -> Collection {
+        let mut collection = Collection::default();
+        for name in names {
+            collection.add_deck(Deck::new(name.to_string()));
+        }
+        collection
+    }
+
+    fn rows(collection: &Collection) -> Vec<(String, usize, Option<usize>, Vec<usize>)> {
+        collection
+            .deck_tree()
+            .into_iter()
+            .map(|node| (node.path, node.depth, node.deck_index, node.decks))
+            .collect()
+    }
+
+    #[test]
+    fn normalizes_deck_names() {
+        assert_eq!(
+            normalize_deck_name(" Języki :: Angielski "),
+            "Języki::Angielski"
+        );
+        assert_eq!(
+            normalize_deck_name("Języki::::Angielski::"),
+            "Języki::Angielski"
+        );
+        assert_eq!(normalize_deck_name(" :: "), "");
+        let collection = collection(&["Języki ::Niemiecki"]);
+        assert_eq!(collection.decks[0].get_name(), "Języki::Niemiecki");
+    }
+
+    #[test]
+    fn builds_tree_with_virtual_parents() {
+        let collection = collection(&[
+            "Języki::Angielski::Czasowniki",
+            "Języki::Niemiecki",
+            "Matematyka",
+            "Języki::Angielski",
+        ]);
+        assert_eq!(
+            rows(&collection),
+            vec![
+                ("Języki".into(), 0, None, vec![0, 1, 3]),
+                ("Języki::Angielski".into(), 1, Some(3), vec![0, 3]),
+                ("Języki::Angielski::Czasowniki".into(), 2, Some(0), vec![0]),
+                ("Języki::Niemiecki".into(), 1, Some(1), vec![1]),
+                ("Matematyka".into(), 0, Some(2), vec![2]),
+            ]
+        );
+        let tree = collection.deck_tree();
+        assert_eq!(tree[1].name, "Angielski");
+        assert!(tree[1].has_children && !tree[2].has_children);
+    }
+
+    #[test]
+    fn subtree_does_not_match_name_prefix() {
+        let collection = collection(&["Język", "Języki", "Język::Polski"]);
+        assert_eq!(collection.subtree_decks("Język"), vec![0, 2]);
+        assert_eq!(parent_path("Język::Polski"), Some("Język"));
+        assert_eq!(parent_path("Język"), None);
+    }
+
+    #[test]
+    fn moves_deck_with_its_children() {
+        let mut collection = collection(&["A", "A::x", "B", "B::y", "C"]);
+        assert!(collection.move_deck("B", true));
+        let names: Vec<String> = collection.decks.iter().map(|d| d.get_name()).collect();
+        assert_eq!(names, vec!["B", "B::y", "A", "A::x", "C"]);
+
+        assert!(!collection.move_deck("B", true));
+        assert!(!collection.move_deck("C", false));
+        assert!(!collection.move_deck("A::x", true));
+    }
+
+    #[test]
+    fn renaming_a_parent_moves_its_children() {
+        let mut collection = collection(&["A", "A::x", "A::x::z", "Ab", "B"]);
+        collection.rename_deck(0, " B :: A ").unwrap();
+        let names: Vec<String> = collection.decks.iter().map(|d| d.get_name()).collect();
+        assert_eq!(names, vec!["B::A", "B::A::x", "B::A::x::z", "Ab", "B"]);
+        assert_eq!(collection.deck_with_descendants(0), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn rejects_moving_a_deck_under_its_descendant() {
+        let mut collection = collection(&["A", "A::x"]);
+        for name in ["A::x::A", "A::nowa"] {
+            assert!(matches!(
+                collection.rename_deck(0, name),
+                Err(CollectionError::MoveIntoDescendant)
+            ));
+        }
+        assert!(matches!(
+            collection.rename_deck(0, " :: "),
+            Err(CollectionError::EmptyDeckName)
+        ));
+        collection.rename_deck(1, "A::y").unwrap();
+        let names: Vec<String> = collection.decks.iter().map(|d| d.get_name()).collect();
+        assert_eq!(names, vec!["A", "A::y"]);
+    }
+}
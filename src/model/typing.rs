@@ -0,0 +1,351 @@
+use super::cloze::cloze_segments;
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnswerMode {
+    #[default]
+    Reveal,
+    Exact,
+    IgnoreCase,
+    Lenient,
+    Choice,
+}
+impl AnswerMode {
+    pub fn next(self) -> AnswerMode {
+        match self {
+            AnswerMode::Reveal => AnswerMode::Lenient,
+            AnswerMode::Lenient => AnswerMode::IgnoreCase,
+            AnswerMode::IgnoreCase => AnswerMode::Exact,
+            AnswerMode::Exact => AnswerMode::Choice,
+            AnswerMode::Choice => AnswerMode::Reveal,
+        }
+    }
+    pub fn is_typed(self) -> bool {
+        !matches!(self, AnswerMode::Reveal | AnswerMode::Choice)
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            AnswerMode::Reveal => "odsłanianie odpowiedzi",
+            AnswerMode::Exact => "wpisywanie (dokładne)",
+            AnswerMode::IgnoreCase => "wpisywanie (bez wielkości liter)",
+            AnswerMode::Lenient => "wpisywanie (bez wielkości liter i polskich znaków)",
+            AnswerMode::Choice => "wybór jednej z kilku odpowiedzi",
+        }
+    }
+    fn fold(self, c: char) -> char {
+        let c = match self {
+            AnswerMode::IgnoreCase | AnswerMode::Lenient => c.to_lowercase().next().unwrap_or(c),
+            _ => c,
+        };
+        match self {
+            AnswerMode::Lenient => strip_diacritic(c),
+            _ => c,
+        }
+    }
+}
+impl fmt::Display for AnswerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnswerMode::Reveal => write!(f, "reveal"),
+            AnswerMode::Exact => write!(f, "exact"),
+            AnswerMode::IgnoreCase => write!(f, "ignore-case"),
+            AnswerMode::Lenient => write!(f, "lenient"),
+            AnswerMode::Choice => write!(f, "choice"),
+        }
+    }
+}
+impl FromStr for AnswerMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reveal" => Ok(AnswerMode::Reveal),
+            "exact" => Ok(AnswerMode::Exact),
+            "ignore-case" => Ok(AnswerMode::IgnoreCase),
+            "lenient" => Ok(AnswerMode::Lenient),
+            "choice" => Ok(AnswerMode::Choice),
+            other => Err(format!("Nieznany tryb odpowiedzi: {}", other)),
+        }
+    }
+}
+
+fn strip_diacritic(c: char) -> char {
+    match c {
+        'ą' | 'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'Ą' | 'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'A',
+        'ć' | 'ç' | 'č' => 'c',
+        'Ć' | 'Ç' | 'Č' => 'C',
+        'ę' | 'è' | 'é' | 'ê' | 'ë' | 'ě' => 'e',
+        'Ę' | 'È' | 'É' | 'Ê' | 'Ë' | 'Ě' => 'E',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'Ì' | 'Í' | 'Î' | 'Ï' => 'I',
+        'ł' => 'l',
+        'Ł' => 'L',
+        'ń' | 'ñ' | 'ň' => 'n',
+        'Ń' | 'Ñ' | 'Ň' => 'N',
+        'ó' | 'ò' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'Ó' | 'Ò' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => 'O',
+        'ś' | 'š' => 's',
+        'Ś' | 'Š' => 'S',
+        'ù' | 'ú' | 'û' | 'ü' | 'ů' => 'u',
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ů' => 'U',
+        'ý' | 'ÿ' => 'y',
+        'Ý' => 'Y',
+        'ź' | 'ż' | 'ž' => 'z',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        'ř' => 'r',
+        'Ř' => 'R',
+        c => c,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Same,
+    Extra,
+    Missing,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffSegment {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnswerCheck {
+    pub expected: String,
+    pub distance: usize,
+    pub diff: Vec<DiffSegment>,
+    pub suggested_rating: u32,
+}
+
+pub fn answer_alternatives(answer: &str) -> Vec<&str> {
+    let mut alternatives: Vec<&str> = answer
+        .split([',', '/', ';', '\n'])
+        .map(str::trim)
+        .filter(|alternative| !alternative.is_empty())
+        .collect();
+    let whole = answer.trim();
+    if alternatives.len() > 1 && !whole.contains('\n') {
+        alternatives.push(whole);
+    }
+    alternatives
+}
+
+fn diff(typed: &[char], expected: &[char], mode: AnswerMode) -> (usize, Vec<DiffSegment>) {
+    let (n, m) = (typed.len(), expected.len());
+    let mut costs = vec![vec![0; m + 1]; n + 1];
+    costs[0] = (0..=m).collect();
+    for (i, row) in costs.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let same = mode.fold(typed[i - 1]) == mode.fold(expected[j - 1]);
+            costs[i][j] = (costs[i - 1][j - 1] + !same as usize)
+                .min(costs[i - 1][j] + 1)
+                .min(costs[i][j - 1] + 1);
+        }
+    }
+    let mut ops = vec![];
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let same = i > 0 && j > 0 && mode.fold(typed[i - 1]) == mode.fold(expected[j - 1]);
+        if same && costs[i][j] == costs[i - 1][j - 1] {
+            ops.push((DiffKind::Same, expected[j - 1]));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && costs[i][j] == costs[i - 1][j - 1] + 1 {
+            ops.push((DiffKind::Missing, expected[j - 1]));
+            ops.push((DiffKind::Extra, typed[i - 1]));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && costs[i][j] == costs[i - 1][j] + 1 {
+            ops.push((DiffKind::Extra, typed[i - 1]));
+            i -= 1;
+        } else {
+            ops.push((DiffKind::Missing, expected[j - 1]));
+            j -= 1;
+        }
+    }
+    let mut segments: Vec<DiffSegment> = vec![];
+    for (kind, c) in ops.into_iter().rev() {
+        match segments.last_mut() {
+            Some(segment) if segment.kind == kind => segment.text.push(c),
+            _ => segments.push(DiffSegment {
+                kind,
+                text: c.to_string(),
+            }),
+        }
+    }
+    (costs[n][m], segments)
+}
+
+fn suggest_rating(distance: usize, expected_len: usize) -> u32 {
+    if distance == 0 {
+        return 4;
+    }
+    match distance as f32 / expected_len.max(1) as f32 {
+        ratio if ratio <= 0.2 => 3,
+        ratio if ratio <= 0.5 => 2,
+        _ => 1,
+    }
+}
+
+pub fn check_answer(typed: &str, answer: &str, mode: AnswerMode) -> AnswerCheck {
+    let typed: Vec<char> = typed.trim().chars().collect();
+    answer_alternatives(answer)
+        .into_iter()
+        .map(|expected| {
+            let chars: Vec<char> = expected.chars().collect();
+            let (distance, diff) = diff(&typed, &chars, mode);
+            AnswerCheck {
+                expected: expected.to_string(),
+                distance,
+                diff,
+                suggested_rating: if typed.is_empty() {
+                    1
+                } else {
+                    suggest_rating(distance, chars.len())
+                },
+            }
+        })
+        .min_by_key(|check| check.distance)
+        .unwrap_or(AnswerCheck {
+            expected: String::new(),
+            distance: typed.len(),
+            diff: vec![],
+            suggested_rating: 1,
+        })
+}
+
+impl FlashCard {
+    pub fn get_expected_answer(&self) -> String {
+        match self.cloze {
+            Some(number) => cloze_segments(&self.question, Some(number), true)
+                .into_iter()
+                .filter(|(_, target)| *target)
+                .map(|(text, _)| text)
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => self.answer.clone(),
+        }
+    }
+}
+
+impl Deck {
+    pub fn get_answer_mode(&self) -> AnswerMode {
+        self.answer_mode
+    }
+    pub fn set_answer_mode(&mut self, mode: AnswerMode) {
+        self.answer_mode = mode;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn render(segments: &[DiffSegment]) -> String {
+        segments
+            .iter()
+            .map(|segment| match segment.kind {
+                DiffKind::Same => segment.text.clone(),
+                DiffKind::Extra => format!("[-{}]", segment.text),
+                DiffKind::Missing => format!("[+{}]", segment.text),
+            })
+            .collect()
+    }
+
+    fn side(segments: &[DiffSegment], kind: DiffKind) -> String {
+        segments
+            .iter()
+            .filter(|segment| segment.kind == DiffKind::Same || segment.kind == kind)
+            .map(|segment| segment.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn computes_levenshtein_distance() {
+        let (distance, _) = diff(&chars("kitten"), &chars("sitting"), AnswerMode::Exact);
+        assert_eq!(distance, 3);
+        let (distance, _) = diff(&chars(""), &chars("kot"), AnswerMode::Exact);
+        assert_eq!(distance, 3);
+        let (distance, _) = diff(&chars("kot"), &chars(""), AnswerMode::Exact);
+        assert_eq!(distance, 3);
+    }
+
+    #[test]
+    fn folds_characters_by_mode() {
+        let distance =
+            |typed: &str, expected: &str, mode| diff(&chars(typed), &chars(expected), mode).0;
+        assert_eq!(distance("Kot", "kot", AnswerMode::Exact), 1);
+        assert_eq!(distance("Kot", "kot", AnswerMode::IgnoreCase), 0);
+        assert_eq!(distance("ZOLW", "żółw", AnswerMode::IgnoreCase), 3);
+        assert_eq!(distance("ZOLW", "żółw", AnswerMode::Lenient), 0);
+    }
+
+    #[test]
+    fn marks_extra_and_missing_characters() {
+        let (_, segments) = diff(&chars("kota"), &chars("kot"), AnswerMode::Exact);
+        assert_eq!(render(&segments), "kot[-a]");
+        let (_, segments) = diff(&chars("samochd"), &chars("samochód"), AnswerMode::Exact);
+        assert_eq!(render(&segments), "samoch[+ó]d");
+        let (_, segments) = diff(&chars("kat"), &chars("kot"), AnswerMode::Exact);
+        assert_eq!(render(&segments), "k[-a][+o]t");
+    }
+
+    #[test]
+    fn diff_rebuilds_both_sides() {
+        for (typed, expected) in [("przyjaciel", "przyjaciół"), ("abc", "xyz"), ("", "pies")] {
+            let (_, segments) = diff(&chars(typed), &chars(expected), AnswerMode::Exact);
+            assert_eq!(side(&segments, DiffKind::Extra), typed);
+            assert_eq!(side(&segments, DiffKind::Missing), expected);
+        }
+    }
+
+    #[test]
+    fn checks_against_closest_alternative() {
+        assert_eq!(
+            answer_alternatives("iść, jechać / pojechać"),
+            vec!["iść", "jechać", "pojechać", "iść, jechać / pojechać"]
+        );
+        assert_eq!(answer_alternatives("a\nb"), vec!["a", "b"]);
+
+        let check = check_answer(" jechac ", "iść, jechać", AnswerMode::Lenient);
+        assert_eq!(check.expected, "jechać");
+        assert_eq!(check.distance, 0);
+        assert_eq!(check.suggested_rating, 4);
+    }
+
+    #[test]
+    fn suggests_rating_from_distance() {
+        let rating = |typed: &str, answer: &str| {
+            check_answer(typed, answer, AnswerMode::Exact).suggested_rating
+        };
+        assert_eq!(rating("samochód", "samochód"), 4);
+        assert_eq!(rating("samochd", "samochód"), 3);
+        assert_eq!(rating("samo", "samochód"), 2);
+        assert_eq!(rating("xyz", "abc"), 1);
+        assert_eq!(rating("", "abc"), 1);
+    }
+
+    #[test]
+    fn answer_mode_round_trips() {
+        let mut mode = AnswerMode::default();
+        for _ in 0..5 {
+            assert_eq!(mode.to_string().parse::<AnswerMode>(), Ok(mode));
+            mode = mode.next();
+        }
+        assert_eq!(mode, AnswerMode::Reveal);
+        assert!("loose".parse::<AnswerMode>().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use super::deck::Deck;
+use super::flashcard::FlashCard;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub struct ChangedCard {
+    pub index: usize,
+    pub question: String,
+    pub answer: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct DeckUpdate {
+    pub added: Vec<FlashCard>,
+    pub changed: Vec<ChangedCard>,
+    pub removed: Vec<usize>,
+    pub unchanged: usize,
+}
+impl DeckUpdate {
+    pub fn compute(deck: &Deck, incoming: Vec<FlashCard>) -> DeckUpdate {
+        let mut unmatched: Vec<bool> = vec![true; deck.cards.len()];
+        let by_id: HashMap<Uuid, usize> = deck
+            .cards
+            .iter()
+            .enumerate()
+            .map(|(i, card)| (card.get_id(), i))
+            .collect();
+        let mut by_question: HashMap<(bool, Option<u32>, String), Vec<usize>> = HashMap::new();
+        for (i, card) in deck.cards.iter().enumerate().rev() {
+            by_question.entry(note_key(card)).or_default().push(i);
+        }
+        let mut notes: HashMap<Uuid, Uuid> = HashMap::new();
+
+        let mut update = DeckUpdate::default();
+        for card in incoming {
+            let matched = by_id
+                .get(&card.get_id())
+                .copied()
+                .filter(|&i| unmatched[i])
+                .or_else(|| {
+                    let candidates = by_question.get_mut(&note_key(&card))?;
+                    while let Some(i) = candidates.pop() {
+                        if unmatched[i] {
+                            return Some(i);
+                        }
+                    }
+                    None
+                })
+                .or_else(|| {
+                    let note_id = notes.get(&card.get_note_id())?;
+                    (0..deck.cards.len()).find(|&i| {
+                        unmatched[i]
+                            && deck.cards[i].get_note_id() == *note_id
+                            && deck.cards[i].is_reversed() == card.is_reversed()
+                            && deck.cards[i].get_cloze() == card.get_cloze()
+                    })
+                });
+            let Some(index) = matched else {
+                update.added.push(card);
+                continue;
+            };
+            unmatched[index] = false;
+            let existing = &deck.cards[index];
+            notes.insert(card.get_note_id(), existing.get_note_id());
+            let tags = if card.tags.is_empty() {
+                existing.tags.clone()
+            } else {
+                card.tags
+            };
+            if existing.question.trim() == card.question.trim()
+                && existing.answer.trim() == card.answer.trim()
+                && existing.tags == tags
+            {
+                update.unchanged += 1;
+            } else {
+                update.changed.push(ChangedCard {
+                    index,
+                    question: card.question,
+                    answer: card.answer,
+                    tags,
+                });
+            }
+        }
+        for card in update.added.iter_mut() {
+            if let Some(note_id) = notes.get(&card.get_note_id()) {
+                card.note_id = Some(*note_id);
+            }
+        }
+        update.removed = (0..deck.cards.len()).filter(|&i| unmatched[i]).collect();
+        update
+    }
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+fn note_key(card: &FlashCard) -> (bool, Option<u32>, String) {
+    let (front, _) = card.get_note_text();
+    (
+        card.is_reversed(),
+        card.get_cloze(),
+        front
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase(),
+    )
+}
+
+impl Deck {
+    pub fn apply_update(&mut self, update: DeckUpdate, retire_removed: bool) {
+        for change in update.changed {
+            if let Some(card) = self.cards.get_mut(change.index) {
+                card.question = change.question;
+                card.answer = change.answer;
+                card.tags = change.tags;
+            }
+        }
+        if retire_removed {
+            for index in update.removed.into_iter().rev() {
+                self.cards.remove(index);
+            }
+        }
+        for card in update.added {
+            self.add_card(card);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use super::{Storage, write_atomic};
+use crate::model::{Collection, QuizLogEntry, ReviewLogEntry};
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub struct JsonStorage {
+    path: PathBuf,
+}
+impl JsonStorage {
+    pub fn new(path: &Path) -> JsonStorage {
+        JsonStorage {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load(&mut self) -> Result<Collection> {
+        match File::open(&self.path) {
+            Ok(file) => {
+                let reader = BufReader::new(file);
+                let collection: Collection = serde_json::from_reader(reader)
+                    .context("Błąd podczas parsowania pliku JSON.")?;
+                Ok(collection)
+            }
+            Err(error) => {
+                if error.kind() == io::ErrorKind::NotFound {
+                    Ok(Collection::default())
+                } else {
+                    Err(anyhow!(error))
+                }
+            }
+        }
+    }
+    fn save(&mut self, collection: &Collection) -> Result<()> {
+        let serialized = serde_json::to_string(collection)?;
+        write_atomic(&self.path, serialized.as_bytes())
+    }
+    fn save_deck(&mut self, collection: &Collection, _deck_index: usize) -> Result<()> {
+        self.save(collection)
+    }
+    fn save_deck_order(&mut self, collection: &Collection) -> Result<()> {
+        self.save(collection)
+    }
+    fn remove_deck(&mut self, collection: &Collection, _deck_id: Uuid) -> Result<()> {
+        self.save(collection)
+    }
+    fn save_review(
+        &mut self,
+        collection: &Collection,
+        _deck_index: usize,
+        _card_index: usize,
+        _entry: &ReviewLogEntry,
+    ) -> Result<()> {
+        self.save(collection)
+    }
+    fn save_reviews(&mut self, collection: &Collection, _entries: &[ReviewLogEntry]) -> Result<()> {
+        self.save(collection)
+    }
+    fn save_quiz_answer(&mut self, collection: &Collection, _entry: &QuizLogEntry) -> Result<()> {
+        self.save(collection)
+    }
+}
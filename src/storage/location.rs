@@ -0,0 +1,119 @@
+use anyhow::{Context, Result, anyhow};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const APP_DIRECTORY: &str = "flashcards-tui";
+const HOME_VARIABLE: &str = "FLASHCARDS_HOME";
+const DECLINED_MARKER: &str = ".legacy-migration-declined";
+const SQLITE_SIDECARS: [&str; 2] = ["-wal", "-shm"];
+
+pub fn resolve_data_dir(override_dir: Option<&Path>) -> Result<PathBuf> {
+    let dir = if let Some(dir) = override_dir {
+        dir.to_path_buf()
+    } else if let Some(dir) = env::var_os(HOME_VARIABLE).filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir)
+    } else if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir).join(APP_DIRECTORY)
+    } else {
+        let home = env::var_os("HOME")
+            .filter(|dir| !dir.is_empty())
+            .ok_or_else(|| anyhow!("Nie można ustalić katalogu domowego (brak zmiennej HOME)."))?;
+        PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join(APP_DIRECTORY)
+    };
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Nie udało się utworzyć katalogu: {}", dir.display()))?;
+    Ok(dir)
+}
+
+pub fn find_legacy_files(legacy_dir: &Path, data_dir: &Path) -> Vec<PathBuf> {
+    let same_dir = match (legacy_dir.canonicalize(), data_dir.canonicalize()) {
+        (Ok(legacy), Ok(data)) => legacy == data,
+        _ => false,
+    };
+    if same_dir
+        || data_dir.join(super::DATABASE_FILENAME).exists()
+        || data_dir.join(DECLINED_MARKER).exists()
+    {
+        return vec![];
+    }
+    [super::DATABASE_FILENAME, super::LEGACY_FILENAME]
+        .iter()
+        .map(|name| legacy_dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+pub fn move_legacy_files(files: &[PathBuf], data_dir: &Path) -> Result<()> {
+    for file in files {
+        let Some(name) = file.file_name() else {
+            continue;
+        };
+        let target = data_dir.join(name);
+        move_file(file, &target)?;
+        for suffix in SQLITE_SIDECARS {
+            let (sidecar, target) = (with_suffix(file, suffix), with_suffix(&target, suffix));
+            if sidecar.exists() {
+                move_file(&sidecar, &target)?;
+            } else if target.exists() {
+                fs::remove_file(&target)
+                    .with_context(|| format!("Nie udało się usunąć pliku: {}", target.display()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn move_file(file: &Path, target: &Path) -> Result<()> {
+    if fs::rename(file, target).is_err() {
+        fs::copy(file, target)
+            .with_context(|| format!("Nie udało się skopiować pliku: {}", file.display()))?;
+        fs::remove_file(file)
+            .with_context(|| format!("Nie udało się usunąć pliku: {}", file.display()))?;
+    }
+    Ok(())
+}
+
+pub fn decline_legacy_migration(data_dir: &Path) -> Result<()> {
+    let marker = data_dir.join(DECLINED_MARKER);
+    fs::write(&marker, "")
+        .with_context(|| format!("Nie udało się zapisać pliku: {}", marker.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::TempDir;
+
+    #[test]
+    fn moves_database_together_with_its_wal() {
+        let (legacy, data) = (TempDir::new(), TempDir::new());
+        let database = legacy.path().join("flashcards.db");
+        fs::write(&database, "baza").unwrap();
+        fs::write(with_suffix(&database, "-wal"), "dziennik").unwrap();
+        let stale = data.path().join("flashcards.db-shm");
+        fs::write(&stale, "stary").unwrap();
+
+        let files = find_legacy_files(legacy.path(), data.path());
+        assert_eq!(files, vec![database.clone()]);
+        move_legacy_files(&files, data.path()).unwrap();
+
+        let moved = data.path().join("flashcards.db");
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "baza");
+        assert_eq!(
+            fs::read_to_string(with_suffix(&moved, "-wal")).unwrap(),
+            "dziennik"
+        );
+        assert!(!stale.exists());
+        assert!(fs::read_dir(legacy.path()).unwrap().next().is_none());
+    }
+}
@@ -0,0 +1,212 @@
+mod json;
+mod location;
+mod sqlite;
+
+use crate::model::{Collection, QuizLogEntry, ReviewLogEntry};
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub use json::JsonStorage;
+pub use location::*;
+pub use sqlite::SqliteStorage;
+use sqlite::is_corrupt;
+
+const DATABASE_FILENAME: &str = "flashcards.db";
+const LEGACY_FILENAME: &str = "deck.json";
+
+pub trait Storage {
+    fn load(&mut self) -> Result<Collection>;
+    fn save(&mut self, collection: &Collection) -> Result<()>;
+    fn save_deck(&mut self, collection: &Collection, deck_index: usize) -> Result<()>;
+    fn save_deck_order(&mut self, collection: &Collection) -> Result<()>;
+    fn remove_deck(&mut self, collection: &Collection, deck_id: Uuid) -> Result<()>;
+    fn save_review(
+        &mut self,
+        collection: &Collection,
+        deck_index: usize,
+        card_index: usize,
+        entry: &ReviewLogEntry,
+    ) -> Result<()>;
+    fn save_reviews(&mut self, collection: &Collection, entries: &[ReviewLogEntry]) -> Result<()>;
+    fn save_quiz_answer(&mut self, collection: &Collection, entry: &QuizLogEntry) -> Result<()>;
+}
+
+pub struct OpenedStorage {
+    pub storage: Box<dyn Storage>,
+    pub collection: Collection,
+    pub warnings: Vec<String>,
+}
+
+pub fn open(dir: &Path) -> Result<OpenedStorage> {
+    let database_path = dir.join(DATABASE_FILENAME);
+    let legacy_path = dir.join(LEGACY_FILENAME);
+    let mut warnings = vec![];
+
+    if !database_path.exists() && legacy_path.exists() {
+        match JsonStorage::new(&legacy_path).load() {
+            Ok(collection) => {
+                let mut storage = SqliteStorage::open(&database_path)?;
+                storage.save(&collection)?;
+                storage.set_setting("migrated_from", &legacy_path.display().to_string())?;
+                fs::rename(&legacy_path, legacy_path.with_extension("json.bak")).with_context(
+                    || {
+                        format!(
+                            "Nie udało się zmienić nazwy pliku: {}",
+                            legacy_path.display()
+                        )
+                    },
+                )?;
+            }
+            Err(error) => {
+                let moved_to = quarantine(&legacy_path)?;
+                warnings.push(format!(
+                    "Plik {} jest uszkodzony i został przeniesiony do {}: {:#}",
+                    legacy_path.display(),
+                    moved_to.display(),
+                    error
+                ));
+            }
+        }
+    }
+
+    let loaded = SqliteStorage::open(&database_path).and_then(|mut storage| {
+        let collection = storage.load()?;
+        Ok((storage, collection))
+    });
+    let (storage, collection) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) if !is_corrupt(&error) => {
+            return Err(error.context(format!(
+                "Nie udało się wczytać bazy danych: {}",
+                database_path.display()
+            )));
+        }
+        Err(error) => {
+            let moved_to = quarantine(&database_path)?;
+            for suffix in ["-wal", "-shm"] {
+                let mut side_file = database_path.clone().into_os_string();
+                side_file.push(suffix);
+                let mut side_target = moved_to.clone().into_os_string();
+                side_target.push(suffix);
+                let _ = fs::rename(side_file, side_target);
+            }
+            warnings.push(format!(
+                "Baza danych {} jest uszkodzona i została przeniesiona do {}: {:#}",
+                database_path.display(),
+                moved_to.display(),
+                error
+            ));
+            (SqliteStorage::open(&database_path)?, Collection::default())
+        }
+    };
+    Ok(OpenedStorage {
+        storage: Box::new(storage),
+        collection,
+        warnings,
+    })
+}
+
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temp_path = path.to_path_buf().into_os_string();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    let mut file = File::create(&temp_path)
+        .with_context(|| format!("Nie udało się utworzyć pliku: {}", temp_path.display()))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+        .with_context(|| format!("Nie udało się zapisać pliku: {}", path.display()))?;
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn quarantine(path: &Path) -> Result<PathBuf> {
+    let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+    let mut target = path.to_path_buf().into_os_string();
+    target.push(format!(".corrupt-{}", timestamp));
+    let target = PathBuf::from(target);
+    fs::rename(path, &target)
+        .with_context(|| format!("Nie udało się przenieść pliku: {}", path.display()))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::{TempDir, collection};
+
+    fn as_json(collection: &Collection) -> String {
+        serde_json::to_string(collection).unwrap()
+    }
+
+    fn quarantined(dir: &Path, prefix: &str) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy()
+                    .starts_with(&format!("{}.corrupt-", prefix))
+            })
+            .count()
+    }
+
+    #[test]
+    fn moves_legacy_json_into_database() {
+        let dir = TempDir::new();
+        let legacy_path = dir.path().join(LEGACY_FILENAME);
+        let collection = collection();
+        JsonStorage::new(&legacy_path).save(&collection).unwrap();
+
+        let opened = open(dir.path()).unwrap();
+        assert_eq!(as_json(&opened.collection), as_json(&collection));
+        assert!(opened.warnings.is_empty());
+        assert!(!legacy_path.exists());
+        assert!(legacy_path.with_extension("json.bak").exists());
+    }
+
+    #[test]
+    fn quarantines_corrupt_database() {
+        let dir = TempDir::new();
+        fs::write(
+            dir.path().join(DATABASE_FILENAME),
+            "to nie jest baza danych".repeat(200),
+        )
+        .unwrap();
+
+        let opened = open(dir.path()).unwrap();
+        assert!(opened.collection.decks.is_empty());
+        assert_eq!(opened.warnings.len(), 1);
+        assert_eq!(quarantined(dir.path(), DATABASE_FILENAME), 1);
+
+        let reopened = open(dir.path()).unwrap();
+        assert!(reopened.warnings.is_empty());
+    }
+
+    #[test]
+    fn quarantines_corrupt_legacy_json() {
+        let dir = TempDir::new();
+        fs::write(dir.path().join(LEGACY_FILENAME), "{\"decks\": [").unwrap();
+
+        let opened = open(dir.path()).unwrap();
+        assert!(opened.collection.decks.is_empty());
+        assert_eq!(opened.warnings.len(), 1);
+        assert_eq!(quarantined(dir.path(), LEGACY_FILENAME), 1);
+    }
+
+    #[test]
+    fn atomic_write_replaces_file() {
+        let dir = TempDir::new();
+        let path = dir.path().join("plik.txt");
+        write_atomic(&path, b"pierwsza").unwrap();
+        write_atomic(&path, b"druga").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "druga");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
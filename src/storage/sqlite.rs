@@ -0,0 +1,564 @@
+use super::Storage;
+use crate::model::{
+    Collection, DailyCounts, Deck, DeckLimits, DeckSource, FlashCard, LearningSteps, QuizLogEntry,
+    ReviewLogEntry, SchedulerKind, format_steps, normalize_tag, parse_steps,
+};
+use anyhow::{Context, Result};
+use rusqlite::{Connection, ErrorCode, Row, Transaction, params};
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+#[error("Baza danych jest uszkodzona: {0}")]
+pub struct CorruptDatabase(String);
+
+pub fn is_corrupt(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.is::<CorruptDatabase>()
+            || matches!(
+                cause.downcast_ref::<rusqlite::Error>(),
+                Some(rusqlite::Error::SqliteFailure(failure, _))
+                    if matches!(failure.code, ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+            )
+    })
+}
+
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE decks (
+        id BLOB PRIMARY KEY,
+        name TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        scheduler TEXT NOT NULL
+    );
+    CREATE TABLE cards (
+        id BLOB PRIMARY KEY,
+        deck_id BLOB NOT NULL REFERENCES decks(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        question TEXT NOT NULL,
+        answer TEXT NOT NULL,
+        ef REAL NOT NULL,
+        repetitions INTEGER NOT NULL,
+        interval INTEGER NOT NULL,
+        stability REAL NOT NULL,
+        difficulty REAL NOT NULL,
+        lapses INTEGER NOT NULL,
+        last_review_date TEXT NOT NULL,
+        next_review_date TEXT NOT NULL
+    );
+    CREATE INDEX cards_deck ON cards(deck_id, position);
+    CREATE TABLE review_log (
+        id BLOB PRIMARY KEY,
+        card_id BLOB NOT NULL,
+        timestamp TEXT NOT NULL,
+        rating INTEGER NOT NULL,
+        previous_interval INTEGER NOT NULL,
+        new_interval INTEGER NOT NULL,
+        previous_ease REAL NOT NULL,
+        new_ease REAL NOT NULL,
+        time_spent_ms INTEGER NOT NULL
+    );
+    CREATE INDEX review_log_card ON review_log(card_id);
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+",
+    "ALTER TABLE cards ADD COLUMN tags TEXT NOT NULL DEFAULT '';",
+    "
+    ALTER TABLE decks ADD COLUMN source_path TEXT;
+    ALTER TABLE decks ADD COLUMN source_format TEXT;
+    ALTER TABLE decks ADD COLUMN source_options TEXT;
+    ALTER TABLE decks ADD COLUMN source_imported_at TEXT;
+    ALTER TABLE decks ADD COLUMN source_modified_at TEXT;
+",
+    "
+    ALTER TABLE cards ADD COLUMN note_id BLOB;
+    ALTER TABLE cards ADD COLUMN reversed INTEGER NOT NULL DEFAULT 0;
+",
+    "ALTER TABLE cards ADD COLUMN cloze INTEGER;",
+    "
+    ALTER TABLE decks ADD COLUMN new_per_day INTEGER NOT NULL DEFAULT 20;
+    ALTER TABLE decks ADD COLUMN reviews_per_day INTEGER NOT NULL DEFAULT 200;
+    ALTER TABLE decks ADD COLUMN daily_date TEXT;
+    ALTER TABLE decks ADD COLUMN daily_new INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE decks ADD COLUMN daily_reviews INTEGER NOT NULL DEFAULT 0;
+",
+    "
+    ALTER TABLE decks ADD COLUMN learning_steps TEXT NOT NULL DEFAULT '1m 10m';
+    ALTER TABLE decks ADD COLUMN relearning_steps TEXT NOT NULL DEFAULT '10m';
+    ALTER TABLE cards ADD COLUMN learning_step INTEGER;
+    ALTER TABLE cards ADD COLUMN due_at TEXT;
+",
+    "ALTER TABLE decks ADD COLUMN answer_mode TEXT NOT NULL DEFAULT 'reveal';",
+    "
+    CREATE TABLE quiz_log (
+        id BLOB PRIMARY KEY,
+        card_id BLOB NOT NULL,
+        timestamp TEXT NOT NULL,
+        correct INTEGER NOT NULL
+    );
+    CREATE INDEX quiz_log_card ON quiz_log(card_id);
+",
+    "ALTER TABLE review_log ADD COLUMN undoes BLOB;",
+    "ALTER TABLE cards ADD COLUMN buried_until TEXT;",
+];
+
+pub struct SqliteStorage {
+    connection: Connection,
+}
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<SqliteStorage> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Nie udało się otworzyć bazy danych: {}", path.display()))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.pragma_update(None, "foreign_keys", "ON")?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "FULL")?;
+        let check: String = connection.pragma_query_value(None, "quick_check", |row| row.get(0))?;
+        if check != "ok" {
+            return Err(CorruptDatabase(check).into());
+        }
+        let mut storage = SqliteStorage { connection };
+        storage.migrate()?;
+        Ok(storage)
+    }
+    fn migrate(&mut self) -> Result<()> {
+        let version: usize = self
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let tx = self.connection.transaction()?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            tx.execute_batch(migration)
+                .with_context(|| format!("Błąd migracji bazy danych do wersji {}.", i + 1))?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+        tx.commit()?;
+        Ok(())
+    }
+    pub fn set_setting(&mut self, key: &str, value: &str) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+    fn insert_deck(tx: &Transaction, deck: &Deck, position: usize) -> Result<()> {
+        let source = deck.source.as_ref();
+        tx.execute(
+            "INSERT INTO decks (id, name, position, scheduler, source_path, source_format,
+                source_options, source_imported_at, source_modified_at, new_per_day,
+                reviews_per_day, daily_date, daily_new, daily_reviews, learning_steps,
+                relearning_steps, answer_mode)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                position = excluded.position,
+                scheduler = excluded.scheduler,
+                source_path = excluded.source_path,
+                source_format = excluded.source_format,
+                source_options = excluded.source_options,
+                source_imported_at = excluded.source_imported_at,
+                source_modified_at = excluded.source_modified_at,
+                new_per_day = excluded.new_per_day,
+                reviews_per_day = excluded.reviews_per_day,
+                daily_date = excluded.daily_date,
+                daily_new = excluded.daily_new,
+                daily_reviews = excluded.daily_reviews,
+                learning_steps = excluded.learning_steps,
+                relearning_steps = excluded.relearning_steps,
+                answer_mode = excluded.answer_mode",
+            params![
+                deck.id,
+                deck.name,
+                position,
+                deck.scheduler.to_string(),
+                source.map(|s| s.path.to_string_lossy().into_owned()),
+                source.map(|s| s.format.to_string()),
+                source.map(|s| s.options.clone()),
+                source.map(|s| s.imported_at),
+                source.and_then(|s| s.modified_at),
+                deck.limits.new_per_day,
+                deck.limits.reviews_per_day,
+                deck.daily.date,
+                deck.daily.new,
+                deck.daily.reviews,
+                format_steps(&deck.steps.learning),
+                format_steps(&deck.steps.relearning),
+                deck.answer_mode.to_string(),
+            ],
+        )?;
+        for (card_position, card) in deck.cards.iter().enumerate() {
+            SqliteStorage::insert_card(tx, deck.id, card_position, card)?;
+        }
+        Ok(())
+    }
+    fn update_positions(tx: &Transaction, collection: &Collection) -> Result<()> {
+        for (position, deck) in collection.decks.iter().enumerate() {
+            tx.execute(
+                "UPDATE decks SET position = ?1 WHERE id = ?2",
+                params![position, deck.id],
+            )?;
+        }
+        Ok(())
+    }
+    fn insert_card(
+        tx: &Transaction,
+        deck_id: Uuid,
+        position: usize,
+        card: &FlashCard,
+    ) -> Result<()> {
+        tx.execute(
+            "INSERT INTO cards (id, deck_id, position, question, answer, ef, repetitions,
+                interval, stability, difficulty, lapses, tags, note_id, reversed, cloze,
+                learning_step, due_at, buried_until, last_review_date, next_review_date)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18, ?19, ?20)
+             ON CONFLICT(id) DO UPDATE SET
+                deck_id = excluded.deck_id,
+                position = excluded.position,
+                question = excluded.question,
+                answer = excluded.answer,
+                ef = excluded.ef,
+                repetitions = excluded.repetitions,
+                interval = excluded.interval,
+                stability = excluded.stability,
+                difficulty = excluded.difficulty,
+                lapses = excluded.lapses,
+                tags = excluded.tags,
+                note_id = excluded.note_id,
+                reversed = excluded.reversed,
+                cloze = excluded.cloze,
+                learning_step = excluded.learning_step,
+                due_at = excluded.due_at,
+                buried_until = excluded.buried_until,
+                last_review_date = excluded.last_review_date,
+                next_review_date = excluded.next_review_date",
+            params![
+                card.id,
+                deck_id,
+                position,
+                card.question,
+                card.answer,
+                card.ef,
+                card.repetitions,
+                card.interval,
+                card.stability,
+                card.difficulty,
+                card.lapses,
+                card.tags
+                    .iter()
+                    .map(|tag| normalize_tag(tag))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                card.note_id,
+                card.reversed,
+                card.cloze,
+                card.learning_step,
+                card.due_at,
+                card.buried_until,
+                card.last_review_date,
+                card.next_review_date,
+            ],
+        )?;
+        Ok(())
+    }
+    fn insert_review(tx: &Transaction, entry: &ReviewLogEntry) -> Result<()> {
+        tx.execute(
+            "INSERT OR IGNORE INTO review_log (id, card_id, timestamp, rating, previous_interval,
+                new_interval, previous_ease, new_ease, time_spent_ms, undoes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                entry.id,
+                entry.card_id,
+                entry.timestamp,
+                entry.rating,
+                entry.previous_interval,
+                entry.new_interval,
+                entry.previous_ease,
+                entry.new_ease,
+                entry.time_spent_ms,
+                entry.undoes,
+            ],
+        )?;
+        Ok(())
+    }
+    fn insert_quiz_answer(tx: &Transaction, entry: &QuizLogEntry) -> Result<()> {
+        tx.execute(
+            "INSERT OR IGNORE INTO quiz_log (id, card_id, timestamp, correct)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![entry.id, entry.card_id, entry.timestamp, entry.correct],
+        )?;
+        Ok(())
+    }
+    fn card_from_row(row: &Row) -> rusqlite::Result<FlashCard> {
+        Ok(FlashCard {
+            id: row.get("id")?,
+            question: row.get("question")?,
+            answer: row.get("answer")?,
+            ef: row.get("ef")?,
+            repetitions: row.get("repetitions")?,
+            interval: row.get("interval")?,
+            stability: row.get("stability")?,
+            difficulty: row.get("difficulty")?,
+            lapses: row.get("lapses")?,
+            tags: row
+                .get::<_, String>("tags")?
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            note_id: row.get("note_id")?,
+            reversed: row.get("reversed")?,
+            cloze: row.get("cloze")?,
+            learning_step: row.get("learning_step")?,
+            due_at: row.get("due_at")?,
+            buried_until: row.get("buried_until")?,
+            last_review_date: row.get("last_review_date")?,
+            next_review_date: row.get("next_review_date")?,
+        })
+    }
+    fn source_from_row(row: &Row) -> rusqlite::Result<Option<DeckSource>> {
+        let Some(path) = row.get::<_, Option<String>>("source_path")? else {
+            return Ok(None);
+        };
+        let format: Option<String> = row.get("source_format")?;
+        let Some(format) = format.and_then(|f| f.parse().ok()) else {
+            return Ok(None);
+        };
+        Ok(Some(DeckSource {
+            path: path.into(),
+            format,
+            options: row
+                .get::<_, Option<String>>("source_options")?
+                .unwrap_or_default(),
+            imported_at: row.get("source_imported_at")?,
+            modified_at: row.get("source_modified_at")?,
+        }))
+    }
+    fn quiz_answer_from_row(row: &Row) -> rusqlite::Result<QuizLogEntry> {
+        Ok(QuizLogEntry {
+            id: row.get("id")?,
+            card_id: row.get("card_id")?,
+            timestamp: row.get("timestamp")?,
+            correct: row.get("correct")?,
+        })
+    }
+    fn review_from_row(row: &Row) -> rusqlite::Result<ReviewLogEntry> {
+        Ok(ReviewLogEntry {
+            id: row.get("id")?,
+            card_id: row.get("card_id")?,
+            timestamp: row.get("timestamp")?,
+            rating: row.get("rating")?,
+            previous_interval: row.get("previous_interval")?,
+            new_interval: row.get("new_interval")?,
+            previous_ease: row.get("previous_ease")?,
+            new_ease: row.get("new_ease")?,
+            time_spent_ms: row.get("time_spent_ms")?,
+            undoes: row.get("undoes")?,
+        })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&mut self) -> Result<Collection> {
+        let mut collection = Collection::default();
+        let mut deck_statement = self
+            .connection
+            .prepare("SELECT * FROM decks ORDER BY position")?;
+        let mut card_statement = self
+            .connection
+            .prepare("SELECT * FROM cards WHERE deck_id = ?1 ORDER BY position")?;
+        let decks = deck_statement.query_map([], |row| {
+            Ok(Deck {
+                id: row.get("id")?,
+                name: row.get("name")?,
+                cards: vec![],
+                scheduler: row
+                    .get::<_, String>("scheduler")?
+                    .parse()
+                    .unwrap_or(SchedulerKind::Sm2),
+                source: SqliteStorage::source_from_row(row)?,
+                limits: DeckLimits {
+                    new_per_day: row.get("new_per_day")?,
+                    reviews_per_day: row.get("reviews_per_day")?,
+                },
+                daily: DailyCounts {
+                    date: row.get("daily_date")?,
+                    new: row.get("daily_new")?,
+                    reviews: row.get("daily_reviews")?,
+                },
+                steps: LearningSteps {
+                    learning: parse_steps(&row.get::<_, String>("learning_steps")?)
+                        .unwrap_or_default(),
+                    relearning: parse_steps(&row.get::<_, String>("relearning_steps")?)
+                        .unwrap_or_default(),
+                },
+                answer_mode: row
+                    .get::<_, String>("answer_mode")?
+                    .parse()
+                    .unwrap_or_default(),
+            })
+        })?;
+        for deck in decks {
+            let mut deck = deck?;
+            deck.cards = card_statement
+                .query_map([deck.id], SqliteStorage::card_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            collection.add_deck(deck);
+        }
+        let mut review_statement = self
+            .connection
+            .prepare("SELECT * FROM review_log ORDER BY timestamp")?;
+        collection.review_log = review_statement
+            .query_map([], SqliteStorage::review_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut quiz_statement = self
+            .connection
+            .prepare("SELECT * FROM quiz_log ORDER BY timestamp")?;
+        collection.quiz_log = quiz_statement
+            .query_map([], SqliteStorage::quiz_answer_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(collection)
+    }
+    fn save(&mut self, collection: &Collection) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        tx.execute("DELETE FROM cards", [])?;
+        tx.execute("DELETE FROM decks", [])?;
+        for (position, deck) in collection.decks.iter().enumerate() {
+            SqliteStorage::insert_deck(&tx, deck, position)?;
+        }
+        for entry in collection.get_review_log() {
+            SqliteStorage::insert_review(&tx, entry)?;
+        }
+        for entry in collection.get_quiz_log() {
+            SqliteStorage::insert_quiz_answer(&tx, entry)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    fn save_deck(&mut self, collection: &Collection, deck_index: usize) -> Result<()> {
+        let Some(deck) = collection.decks.get(deck_index) else {
+            return Ok(());
+        };
+        let tx = self.connection.transaction()?;
+        tx.execute("DELETE FROM cards WHERE deck_id = ?1", [deck.id])?;
+        SqliteStorage::insert_deck(&tx, deck, deck_index)?;
+        tx.commit()?;
+        Ok(())
+    }
+    fn save_deck_order(&mut self, collection: &Collection) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        SqliteStorage::update_positions(&tx, collection)?;
+        tx.commit()?;
+        Ok(())
+    }
+    fn remove_deck(&mut self, collection: &Collection, deck_id: Uuid) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        tx.execute("DELETE FROM decks WHERE id = ?1", [deck_id])?;
+        SqliteStorage::update_positions(&tx, collection)?;
+        tx.commit()?;
+        Ok(())
+    }
+    fn save_review(
+        &mut self,
+        collection: &Collection,
+        deck_index: usize,
+        card_index: usize,
+        entry: &ReviewLogEntry,
+    ) -> Result<()> {
+        let Some(deck) = collection.decks.get(deck_index) else {
+            return Ok(());
+        };
+        let tx = self.connection.transaction()?;
+        tx.execute(
+            "UPDATE decks SET daily_date = ?1, daily_new = ?2, daily_reviews = ?3 WHERE id = ?4",
+            params![deck.daily.date, deck.daily.new, deck.daily.reviews, deck.id],
+        )?;
+        if let Some(card) = deck.get_card(card_index) {
+            SqliteStorage::insert_card(&tx, deck.id, card_index, card)?;
+        }
+        for sibling in deck.get_siblings(card_index) {
+            SqliteStorage::insert_card(&tx, deck.id, sibling, &deck.cards[sibling])?;
+        }
+        SqliteStorage::insert_review(&tx, entry)?;
+        tx.commit()?;
+        Ok(())
+    }
+    fn save_reviews(&mut self, _collection: &Collection, entries: &[ReviewLogEntry]) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        for entry in entries {
+            SqliteStorage::insert_review(&tx, entry)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    fn save_quiz_answer(&mut self, _collection: &Collection, entry: &QuizLogEntry) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        SqliteStorage::insert_quiz_answer(&tx, entry)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::testing::{TempDir, card, collection, now};
+    use std::time::Duration;
+
+    fn as_json(collection: &Collection) -> String {
+        serde_json::to_string(collection).unwrap()
+    }
+
+    #[test]
+    fn new_database_is_fully_migrated() {
+        let dir = TempDir::new();
+        let path = dir.path().join("test.db");
+        let storage = SqliteStorage::open(&path).unwrap();
+        let version: usize = storage
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        drop(storage);
+        SqliteStorage::open(&path).unwrap();
+    }
+
+    #[test]
+    fn collection_survives_save_and_load() {
+        let dir = TempDir::new();
+        let path = dir.path().join("test.db");
+        let collection = collection();
+        SqliteStorage::open(&path)
+            .unwrap()
+            .save(&collection)
+            .unwrap();
+        let loaded = SqliteStorage::open(&path).unwrap().load().unwrap();
+        assert_eq!(as_json(&loaded), as_json(&collection));
+    }
+
+    #[test]
+    fn saves_single_reviews_and_decks() {
+        let dir = TempDir::new();
+        let mut storage = SqliteStorage::open(&dir.path().join("test.db")).unwrap();
+        let mut collection = collection();
+        storage.save(&collection).unwrap();
+
+        let later = now() + chrono::Duration::minutes(5);
+        let entry = collection
+            .review_card(0, 1, 5, later, Duration::ZERO)
+            .unwrap()
+            .clone();
+        storage.save_review(&collection, 0, 1, &entry).unwrap();
+        assert_eq!(as_json(&storage.load().unwrap()), as_json(&collection));
+
+        collection.decks[1].add_card(card("ryba", "fish"));
+        storage.save_deck(&collection, 1).unwrap();
+        assert_eq!(as_json(&storage.load().unwrap()), as_json(&collection));
+    }
+}
@@ -1,44 +1,140 @@
-use crate::app::{App, CurrentScreen, CurrentlyEditing};
-use chrono::Utc;
+use crate::app::{
+    App, BrowserColumn, CurrentScreen, CurrentlyEditing, DeckPromptKind, EditorField,
+    NotificationKind,
+};
+use crate::model::{
+    AnswerMode, DiffKind, FORECAST_DAYS, HEATMAP_WEEKS, HISTORY_DAYS, StudyCounts, cloze_numbers,
+    cloze_segments,
+};
+use chrono::{Datelike, Duration};
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::{Modifier, Span, Style};
 use ratatui::style::palette::tailwind::SLATE;
 use ratatui::style::{Color, Stylize};
 use ratatui::text::Line;
-use ratatui::widgets::{Block, BorderType, Borders, List, ListItem, Padding, Paragraph, Wrap};
+use ratatui::widgets::{
+    Bar, BarChart, BarGroup, Block, BorderType, Borders, Cell, Clear, List, ListItem, Padding,
+    Paragraph, Row, Sparkline, Table, Wrap,
+};
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .constraints([Constraint::Min(1), Constraint::Length(4)])
         .split(frame.area());
 
     match app.current_screen {
         CurrentScreen::Main => draw_main_menu(frame, app, chunks[0]),
         CurrentScreen::Studying => draw_study_view(frame, app, chunks[0]),
+        CurrentScreen::Browsing => draw_browser(frame, app, chunks[0]),
+        CurrentScreen::EditingCard => draw_card_editor(frame, app, chunks[0]),
+        CurrentScreen::Statistics => draw_statistics(frame, app, chunks[0]),
+        CurrentScreen::DeckPrompt => {
+            draw_main_menu(frame, app, chunks[0]);
+            draw_deck_prompt(frame, app);
+        }
+        CurrentScreen::ConfirmingDeckDeletion => {
+            draw_main_menu(frame, app, chunks[0]);
+            draw_deck_deletion_confirmation(frame, app);
+        }
+        CurrentScreen::UpdatePreview => {
+            draw_main_menu(frame, app, chunks[0]);
+            draw_update_preview(frame, app);
+        }
+        CurrentScreen::SourceChanged => {
+            draw_main_menu(frame, app, chunks[0]);
+            draw_source_change(frame, app);
+        }
         _ => draw_main_menu(frame, app, chunks[0]),
     }
 
     let current_keys_hint = {
         match app.current_screen {
+            CurrentScreen::Main if app.merge_source.is_some() => Span::styled(
+                "Wybierz talię docelową i naciśnij 'm', aby scalić | Esc: Anuluj",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Main if app.tags_focused => Span::styled(
+                "↑↓: Wybierz tag | Enter: Filtruj talie tym tagiem | Tab/Esc: Powrót do talii",
+                Style::default().fg(Color::Red),
+            ),
             CurrentScreen::Main => Span::styled(
-                "Nawigacja: ↑↓ | Enter: Ucz się | 'a': Dodaj talię | 'q': Wyjdź",
+                "Nawigacja: ↑↓ | Enter: Ucz się | 'w': Ucz się ze wszystkich talii | ←→: Zwiń/rozwiń | Tab/'f': Tagi / filtr | 'a': Dodaj talię | 's': Algorytm | 'o': Limity dzienne | 'p': Kroki nauki | 'i': Tryb odpowiedzi | 'c': Quiz | 'n': Nowa karta | 'b'/'B': Przeglądaj | 'r': Zmień nazwę | 'd': Usuń | Shift+↑↓: Przesuń | 'm': Scal | 't': Statystyki | 'e'/'E': Eksport talii/kolekcji | 'u'/'l': Aktualizuj / wczytaj ponownie ze źródła | Ctrl+Z/Ctrl+Y: Cofnij/ponów | 'q': Wyjdź",
                 Style::default().fg(Color::Red),
             ),
             CurrentScreen::AddingDeck => Span::styled(
                 "Enter: Zatwierdź | Esc: Anuluj | Tab: Zmień pudełko",
                 Style::default().fg(Color::Red),
             ),
+            CurrentScreen::Studying if app.is_quiz_practice() => Span::styled(
+                if app.is_choosing_answer() {
+                    "1-4: Wybierz odpowiedź | Esc: Zakończ quiz"
+                } else {
+                    "Enter/Space: Dalej | Esc: Zakończ quiz"
+                },
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Studying if app.is_choosing_answer() => Span::styled(
+                "1-4: Wybierz odpowiedź | Space: Pokaż odpowiedź | Ctrl+Z: Cofnij ocenę | Esc: Powrót",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Studying if app.is_typing_answer() => Span::styled(
+                "Wpisz odpowiedź | Enter: Sprawdź | Ctrl+Z: Cofnij ocenę | Esc: Powrót",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Studying
+                if app.study_state.as_ref().is_some_and(|state| {
+                    state.answer_check.is_some() || state.chosen.is_some()
+                }) =>
+            {
+                Span::styled(
+                    "1-5: Oceń | Enter: Sugerowana ocena | Ctrl+Z: Cofnij ocenę | Esc: Powrót",
+                    Style::default().fg(Color::Red),
+                )
+            }
             CurrentScreen::Studying => Span::styled(
-                "Space: Pokaż odpowiedź | 1-5: Oceń | Esc: Powrót",
+                "Space: Pokaż odpowiedź | 1-5: Oceń | Ctrl+Z: Cofnij ocenę | Ctrl+Y: Ponów | Esc: Powrót",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Browsing => Span::styled(
+                "↑↓ | ←→/'s': Sortuj | 'f': Filtr | '/': Szukaj | 'e': Edytuj | 'n': Nowa | 'd': Usuń | 'k': Kierunek kart | Spacja: Zaznacz | 'x': Nowa talia z zaznaczonych | Esc: Powrót",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::EditingCard => Span::styled(
+                "Ctrl+S: Zapisz | Tab: Zmień pole (pytanie/odpowiedź/tagi) | Ctrl+K: Kierunek kart | Enter: Nowa linia | Esc: Anuluj",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::Statistics => Span::styled(
+                "Tab: Talia / cała kolekcja | Esc: Powrót",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::DeckPrompt => Span::styled(
+                "Enter: Zatwierdź | Esc: Anuluj",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::ConfirmingDeckDeletion => Span::styled(
+                "'t': Usuń | Dowolny klawisz: Anuluj",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::SourceChanged => Span::styled(
+                "'t': Pokaż zmiany | Dowolny klawisz: Pomiń",
+                Style::default().fg(Color::Red),
+            ),
+            CurrentScreen::UpdatePreview => Span::styled(
+                "Enter: Zastosuj | 'r': Usuwaj karty spoza źródła | ↑↓: Przewiń | Esc: Anuluj",
                 Style::default().fg(Color::Red),
             ),
             CurrentScreen::Exiting => Span::styled("", Style::default().fg(Color::Red)),
         }
     };
+    let current_keys_hint = match &app.message {
+        Some(message) => Span::styled(message.clone(), Style::default().fg(Color::Yellow)),
+        None => current_keys_hint,
+    };
     let key_notes_footer = Paragraph::new(Line::from(current_keys_hint))
         .centered()
+        .wrap(Wrap { trim: true })
         .block(
             Block::default()
                 .border_type(BorderType::Rounded)
@@ -52,23 +148,35 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
             .borders(Borders::ALL)
             .style(Style::default().bg(Color::DarkGray));
 
-        let area = centered_rect(60, 25, frame.area());
+        let area = centered_rect(60, 35, frame.area());
         frame.render_widget(popup_block, area);
 
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
+            .split(area);
         let popup_chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .margin(1)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(area);
+            .split(rows[0]);
 
         let mut key_block = Block::default().title("Nazwa").borders(Borders::ALL);
         let mut value_block = Block::default().title("Ścieżka").borders(Borders::ALL);
+        let mut options_block = Block::default()
+            .title("Opcje importu (opcjonalne)")
+            .borders(Borders::ALL);
 
         let active_style = Style::default().bg(Color::LightBlue).fg(Color::Black);
 
         match editing {
             CurrentlyEditing::Name => key_block = key_block.style(active_style),
             CurrentlyEditing::Path => value_block = value_block.style(active_style),
+            CurrentlyEditing::Options => options_block = options_block.style(active_style),
         };
 
         let key_text = Paragraph::new(app.name_input.clone()).block(key_block);
@@ -76,7 +184,19 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
 
         let value_text = Paragraph::new(app.path_input.clone()).block(value_block);
         frame.render_widget(value_text, popup_chunks[1]);
+
+        let options_text = Paragraph::new(app.options_input.clone()).block(options_block);
+        frame.render_widget(options_text, rows[1]);
+        frame.render_widget(
+            Paragraph::new(
+                "np. cards=both (karty w obie strony), dla CSV/TSV także sep=; header=tak \
+                 question=2 answer=1 tags=3 quote=nie (kolumny numerem lub nazwą z nagłówka)",
+            )
+            .wrap(Wrap { trim: true }),
+            rows[2],
+        );
     }
+    draw_notification(frame, app);
 }
 
 fn draw_study_view(frame: &mut Frame, app: &mut App, area: Rect) {
@@ -90,80 +210,848 @@ fn draw_study_view(frame: &mut Frame, app: &mut App, area: Rect) {
         .split(area);
     let card_area = vertical_chunks[0];
 
-    if let Some(state) = &app.study_state {
-        if let Some(deck) = app.collection.decks.get(state.deck_index) {
-            if let Some(card) = deck.get_card(state.indexes[state.card_index]) {
-                let title: Line = Line::from(vec!["Uczysz się: ".into(), deck.get_name().into()])
-                    .centered()
-                    .style(Style::default().fg(Color::Red));
-                let block = Block::default()
-                    .borders(Borders::ALL)
-                    .title(title)
-                    .border_type(BorderType::Rounded)
-                    .padding(Padding::new(0, 0, card_area.height / 2, 0));
-                let mut text_lines: Vec<Span> = vec![];
-                if state.is_answer_visible {
-                    text_lines.push("Odpowiedź: ".bold().bold().green());
-                    text_lines.push(card.get_answer().green().into());
-                } else {
-                    text_lines = vec![
-                        "Pytanie: \
-                        "
-                        .bold(),
-                        "\n".into(),
-                        card.get_question().into(),
-                        "\n".into(),
-                    ];
+    if let Some(state) = &app.study_state
+        && let Some((deck_idx, card_idx)) = state.indexes.get(state.card_index).copied()
+        && let Some(deck) = app.collection.decks.get(deck_idx)
+        && let Some(card) = deck.get_card(card_idx)
+    {
+        let mut title = vec![
+            if state.practice {
+                "Quiz: "
+            } else {
+                "Uczysz się: "
+            }
+            .into(),
+            deck.get_name().into(),
+        ];
+        if let Some(filter) = &state.tag_filter {
+            title.push(format!(" | Tagi: {}", filter).into());
+        }
+        let title: Line = Line::from(title)
+            .centered()
+            .style(Style::default().fg(Color::Red));
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::new(0, 0, card_area.height / 2, 0));
+        let mut text_lines: Vec<Span> = vec![];
+        if let Some(number) = card.get_cloze() {
+            let visible = state.is_answer_visible;
+            text_lines.push(if visible {
+                "Odpowiedź: ".bold().green()
+            } else {
+                "Pytanie: ".bold()
+            });
+            for (text, target) in cloze_segments(&card.get_question(), Some(number), visible) {
+                text_lines.push(match (target, visible) {
+                    (true, true) => text.bold().green(),
+                    (true, false) => text.bold().yellow(),
+                    _ => text.into(),
+                });
+            }
+            if visible && !card.get_answer().trim().is_empty() {
+                text_lines.push(" — ".into());
+                text_lines.push(card.get_answer().green());
+            }
+        } else if state.is_answer_visible {
+            text_lines.push("Odpowiedź: ".bold().bold().green());
+            text_lines.push(card.get_answer().green());
+        } else {
+            text_lines = vec![
+                "Pytanie: \
+                "
+                .bold(),
+                "\n".into(),
+                card.get_question().into(),
+                "\n".into(),
+            ];
+        }
+        let mut lines: Vec<Line> = vec![text_lines.into()];
+        if let Some(quiz) = &state.quiz {
+            lines.push(Line::default());
+            for (i, choice) in quiz.choices.iter().enumerate() {
+                let text = format!("{}. {}", i + 1, choice.replace('\n', " / "));
+                lines.push(Line::from(match state.chosen {
+                    _ if state.is_answer_visible && quiz.is_correct(i) => text.bold().green(),
+                    Some(chosen) if chosen == i => text.red().crossed_out(),
+                    _ => text.into(),
+                }));
+            }
+            if let Some(chosen) = state.chosen
+                && !state.practice
+            {
+                let rating = if quiz.is_correct(chosen) { 4 } else { 1 };
+                lines.push(Line::default());
+                lines.push(Line::from(vec![
+                    "Sugerowana ocena: ".into(),
+                    rating.to_string().bold(),
+                    " (Enter: zatwierdź)".into(),
+                ]));
+            }
+        } else if deck.get_answer_mode().is_typed() && !state.practice {
+            lines.push(Line::default());
+            match &state.answer_check {
+                None => lines.push(Line::from(vec![
+                    "Twoja odpowiedź: ".bold(),
+                    state.typed_answer.clone().into(),
+                    "▏".into(),
+                ])),
+                Some(check) => {
+                    let mut typed = vec!["Twoja odpowiedź: ".bold()];
+                    typed.extend(check.diff.iter().map(|segment| match segment.kind {
+                        DiffKind::Same => segment.text.clone().green(),
+                        DiffKind::Extra => segment.text.clone().red().crossed_out(),
+                        DiffKind::Missing => segment.text.clone().yellow().underlined(),
+                    }));
+                    lines.push(typed.into());
+                    if check.expected.trim() != card.get_expected_answer().trim() {
+                        lines.push(format!("Porównano z: {}", check.expected).into());
+                    }
+                    lines.push(Line::from(vec![
+                        "Sugerowana ocena: ".into(),
+                        check.suggested_rating.to_string().bold(),
+                        format!(" (błędów: {}, Enter: zatwierdź)", check.distance).into(),
+                    ]));
                 }
-                let card_paragraph = Paragraph::new::<Line>(text_lines.into())
-                    .block(block)
-                    .wrap(Wrap { trim: true })
-                    .centered();
-
-                frame.render_widget(card_paragraph, card_area);
-                let block_info = Block::default()
-                    .borders(Borders::NONE)
-                    .padding(Padding::new(0, 0, vertical_chunks[1].height / 2, 0));
-                let current_card_num: Span = (state.card_index + 1).to_string().into();
-                let all_card_num: Span = (state.indexes.len()).to_string().into();
-                let cards_paragraph_info = Paragraph::new::<Line>(
-                    vec!["Karta: ".into(), current_card_num, "/".into(), all_card_num].into(),
-                )
-                .block(block_info)
-                .centered();
-                frame.render_widget(cards_paragraph_info, vertical_chunks[1]);
             }
         }
+        let card_paragraph = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .centered();
+
+        frame.render_widget(card_paragraph, card_area);
+        let block_info = Block::default()
+            .borders(Borders::NONE)
+            .padding(Padding::new(0, 0, vertical_chunks[1].height / 2, 0));
+        let current_card_num: Span = (state.card_index + 1).to_string().into();
+        let all_card_num: Span = (state.indexes.len()).to_string().into();
+        let mut info = vec![
+            "Karta: ".into(),
+            current_card_num,
+            "/".into(),
+            all_card_num,
+            format!(" | Pozostało: {}", state.remaining()).into(),
+        ];
+        let requeued = state.requeued_remaining();
+        if requeued > 0 {
+            info.push(format!(" (w tym powtórzone: {})", requeued).yellow());
+        }
+        if state.practice {
+            let (correct, answers) = state.quiz_score;
+            info.push(format!(" | Poprawne: {}/{}", correct, answers).green());
+        } else if let Some(due_at) = card.get_due_at() {
+            info.push(format!(" | W nauce (termin {})", due_at.format("%H:%M")).red());
+        }
+        let cards_paragraph_info = Paragraph::new::<Line>(info.into())
+            .block(block_info)
+            .centered();
+        frame.render_widget(cards_paragraph_info, vertical_chunks[1]);
     }
 }
 
 fn draw_main_menu(frame: &mut Frame, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(1), Constraint::Length(32)])
+        .split(area);
+    let filter = app.tag_filter.as_ref().map(|filter| &filter.expr);
+    let now = chrono::Local::now().naive_local();
     let deck_items: Vec<ListItem> = app
-        .collection
-        .decks
-        .iter()
-        .map(|d| {
-            ListItem::new(format!(
-                "{:<25} ({} kart) ({} kart do powtórki)",
-                d.get_name(),
-                d.get_card_count(),
-                d.get_review_count(Utc::now().date_naive())
-            ))
+        .deck_rows()
+        .into_iter()
+        .map(|row| {
+            let decks = row.decks.iter().map(|&i| &app.collection.decks[i]);
+            let marker = match (row.has_children, app.collapsed_decks.contains(&row.path)) {
+                (false, _) => "  ",
+                (true, false) => "▾ ",
+                (true, true) => "▸ ",
+            };
+            let label = format!("{}{}{}", "  ".repeat(row.depth), marker, row.name);
+            let settings = row
+                .deck_index
+                .map(|i| &app.collection.decks[i])
+                .map(|d| {
+                    let typed = match d.get_answer_mode() {
+                        AnswerMode::Choice => ", wybór",
+                        mode if mode.is_typed() => ", wpisywanie",
+                        _ => "",
+                    };
+                    format!(
+                        " [{}, limit {}{}]",
+                        d.get_scheduler(),
+                        d.get_limits(),
+                        typed
+                    )
+                })
+                .unwrap_or_default();
+            let cards: usize = decks
+                .clone()
+                .map(|d| d.get_card_count_matching(filter))
+                .sum();
+            let counts = decks
+                .map(|d| d.get_study_counts(now, filter))
+                .fold(StudyCounts::default(), |sum, counts| sum + counts);
+            let item = ListItem::new(Line::from(vec![
+                format!("{:<25} ({} kart) ", label, cards).into(),
+                counts.new.to_string().blue(),
+                " / ".into(),
+                counts.learning.to_string().red(),
+                " / ".into(),
+                counts.review.to_string().green(),
+                settings.into(),
+            ]));
+            let deck_id = row.deck_index.map(|i| app.collection.decks[i].get_id());
+            if deck_id.is_some() && app.merge_source == deck_id {
+                item.style(Style::default().fg(Color::Yellow))
+            } else {
+                item
+            }
         })
         .collect();
 
+    let source = app
+        .selected_deck_index()
+        .and_then(|idx| app.collection.decks[idx].get_source())
+        .map(|source| {
+            format!(
+                " Źródło: {} ({}), wczytano {}{} ",
+                source.path.display(),
+                source.format,
+                source.imported_at.format("%Y-%m-%d %H:%M"),
+                if source.has_changed() {
+                    " — plik zmienił się od tego czasu"
+                } else {
+                    ""
+                }
+            )
+        })
+        .unwrap_or_default();
+    let deck_title = match &app.tag_filter {
+        Some(filter) => format!(
+            "Twoje talie (nowe / nauka / powtórki) | Tagi: {}",
+            filter.text
+        ),
+        None => "Twoje talie (nowe / nauka / powtórki)".to_string(),
+    };
     let deck_list = List::new(deck_items)
         .block(
             Block::default()
-                .title(Line::raw("Twoje talie").centered())
+                .title(Line::raw(deck_title).centered())
+                .title_bottom(Line::raw(source))
                 .border_type(BorderType::Rounded)
                 .borders(Borders::ALL),
         )
         .highlight_style(Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD))
         .highlight_symbol("> ");
 
-    frame.render_stateful_widget(deck_list, area, &mut app.deck_list_state);
+    frame.render_stateful_widget(deck_list, chunks[0], &mut app.deck_list_state);
+
+    let tag_items: Vec<ListItem> = app
+        .collection
+        .tag_counts(now)
+        .into_iter()
+        .map(|count| ListItem::new(format!("{} ({}/{})", count.tag, count.due, count.cards)))
+        .collect();
+    let tag_border = if app.tags_focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    let tag_list = List::new(tag_items)
+        .block(
+            Block::default()
+                .title(Line::raw("Tagi (do powtórki/kart)").centered())
+                .border_type(BorderType::Rounded)
+                .borders(Borders::ALL)
+                .border_style(tag_border),
+        )
+        .highlight_style(if app.tags_focused {
+            Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD)
+        } else {
+            Style::new()
+        })
+        .highlight_symbol(if app.tags_focused { "> " } else { "  " });
+    frame.render_stateful_widget(tag_list, chunks[1], &mut app.tag_list_state);
+}
+fn draw_browser(frame: &mut Frame, app: &mut App, area: Rect) {
+    let rows = app.browser_rows();
+    let Some(state) = &app.browser_state else {
+        return;
+    };
+    let scope = match state
+        .deck_index
+        .and_then(|idx| app.collection.decks.get(idx))
+    {
+        Some(deck) => deck.get_name(),
+        None => "Wszystkie talie".to_string(),
+    };
+    let search_cursor = if state.is_searching { "_" } else { "" };
+    let title = Line::from(format!(
+        "{} | Filtr: {} | Szukaj: {}{} | {} kart",
+        scope,
+        state.filter.label(),
+        state.query,
+        search_cursor,
+        rows.len()
+    ))
+    .centered();
+
+    let header = Row::new(BrowserColumn::ALL.iter().map(|column| {
+        let mut title = column.title().to_string();
+        if *column == state.sort_column {
+            title.push_str(if state.sort_ascending { " ▲" } else { " ▼" });
+            Cell::from(title).style(Style::default().fg(Color::Yellow))
+        } else {
+            Cell::from(title)
+        }
+    }))
+    .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let today = chrono::Local::now().date_naive();
+    let table_rows: Vec<Row> = rows
+        .iter()
+        .filter_map(|(deck_idx, card_idx)| app.collection.decks[*deck_idx].get_card(*card_idx))
+        .map(|card| {
+            let mut question = card.render_question();
+            if card.is_reversed() {
+                question = format!("⇄ {}", question);
+            }
+            if state.marked.contains(&card.get_id()) {
+                question = format!("● {}", question);
+            }
+            let due_style = if card.get_date() < today && !card.is_new() {
+                Style::default().fg(Color::Red)
+            } else if card.get_date() <= today {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(question),
+                Cell::from(card.render_answer()),
+                Cell::from(card.get_date().to_string()).style(due_style),
+                Cell::from(format!("{} d", card.get_interval())),
+                Cell::from(format!("{:.2}", card.get_ef())),
+                Cell::from(card.get_repetitions().to_string()),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        table_rows,
+        [
+            Constraint::Percentage(30),
+            Constraint::Percentage(30),
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Length(9),
+            Constraint::Length(13),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title(title)
+            .border_type(BorderType::Rounded)
+            .borders(Borders::ALL),
+    )
+    .row_highlight_style(Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD))
+    .highlight_symbol("> ");
+
+    if let Some(state) = &mut app.browser_state {
+        frame.render_stateful_widget(table, area, &mut state.table_state);
+        if state.confirm_delete {
+            let popup_area = centered_rect(40, 20, area);
+            let popup = Paragraph::new("Usunąć zaznaczoną kartę? (t/n)")
+                .centered()
+                .block(
+                    Block::default()
+                        .title("Potwierdzenie")
+                        .border_type(BorderType::Rounded)
+                        .borders(Borders::ALL)
+                        .padding(Padding::new(0, 0, popup_area.height / 3, 0))
+                        .style(Style::default().bg(Color::DarkGray)),
+                );
+            frame.render_widget(Clear, popup_area);
+            frame.render_widget(popup, popup_area);
+        }
+    }
+}
+
+fn draw_card_editor(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(state) = &app.editor_state else {
+        return;
+    };
+    let deck_name = app
+        .collection
+        .decks
+        .get(state.deck_index)
+        .map(|deck| deck.get_name())
+        .unwrap_or_default();
+    let title = if state.card_index.is_some() {
+        "Edycja karty: "
+    } else {
+        "Nowa karta: "
+    };
+    let outer = Block::default()
+        .title(
+            Line::from(vec![
+                title.into(),
+                deck_name.into(),
+                match cloze_numbers(&state.question).len() {
+                    0 => format!(" | Karty: {}", state.direction.label()),
+                    count => format!(" | Karty: luki ({})", count),
+                }
+                .into(),
+            ])
+            .centered(),
+        )
+        .border_type(BorderType::Rounded)
+        .borders(Borders::ALL);
+    let inner_area = outer.inner(area);
+    frame.render_widget(outer, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(50),
+            Constraint::Min(3),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .split(inner_area);
+
+    let active_style = Style::default().bg(Color::LightBlue).fg(Color::Black);
+    let fields = [
+        (EditorField::Question, "Pytanie", &state.question),
+        (EditorField::Answer, "Odpowiedź", &state.answer),
+        (EditorField::Tags, "Tagi (oddzielone spacją)", &state.tags),
+    ];
+    for (i, (field, label, text)) in fields.into_iter().enumerate() {
+        let mut block = Block::default()
+            .title(label)
+            .border_type(BorderType::Rounded)
+            .borders(Borders::ALL);
+        let mut content = text.clone();
+        if state.focus == field {
+            block = block.style(active_style);
+            content.push('_');
+        }
+        let paragraph = Paragraph::new(content)
+            .block(block)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, chunks[i]);
+    }
+    if let Some(error) = &state.error {
+        frame.render_widget(
+            Paragraph::new(error.clone())
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: true }),
+            chunks[3],
+        );
+    }
+}
+fn draw_statistics(frame: &mut Frame, app: &App, area: Rect) {
+    let today = chrono::Local::now().date_naive();
+    let stats = app.collection.statistics(app.statistics_deck, today);
+    let scope = match app
+        .statistics_deck
+        .and_then(|idx| app.collection.decks.get(idx))
+    {
+        Some(deck) => deck.get_name(),
+        None => "Cała kolekcja".to_string(),
+    };
+    let outer = Block::default()
+        .title(Line::from(vec!["Statystyki: ".into(), scope.into()]).centered())
+        .border_type(BorderType::Rounded)
+        .borders(Borders::ALL);
+    let inner = outer.inner(area);
+    frame.render_widget(outer, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(5),
+            Constraint::Percentage(40),
+            Constraint::Min(9),
+        ])
+        .split(inner);
+
+    let retention = stats
+        .retention
+        .map(|r| format!("{:.1}%", r * 100.0))
+        .unwrap_or_else(|| "-".to_string());
+    let summary = Paragraph::new(vec![
+        Line::from(format!(
+            "Karty: {} (nowe: {}) | Powtórki łącznie: {} | Skuteczność (ocena ≥ 3): {}",
+            stats.card_count, stats.new_count, stats.total_reviews, retention
+        )),
+        Line::from(format!(
+            "Powtórki dzisiaj: {} | W ciągu {} dni: {} | Quiz: {}/{} poprawnych",
+            stats.reviews_per_day.last().copied().unwrap_or(0),
+            HISTORY_DAYS,
+            stats.reviews_per_day.iter().sum::<u64>(),
+            stats.quiz_correct,
+            stats.quiz_answers
+        )),
+    ])
+    .centered()
+    .block(Block::default().padding(Padding::top(1)));
+    frame.render_widget(summary, rows[0]);
+
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .title(format!("Powtórki dziennie (ostatnie {} dni)", HISTORY_DAYS))
+                .borders(Borders::ALL),
+        )
+        .data(&stats.reviews_per_day)
+        .style(Style::default().fg(Color::Green));
+    frame.render_widget(sparkline, rows[1]);
+
+    let forecast_bars: Vec<Bar> = stats
+        .forecast
+        .iter()
+        .enumerate()
+        .map(|(day, count)| {
+            Bar::default().value(*count).label(Line::from(
+                (today + Duration::days(day as i64)).day().to_string(),
+            ))
+        })
+        .collect();
+    let forecast = BarChart::default()
+        .block(
+            Block::default()
+                .title(format!("Prognoza powtórek ({} dni)", FORECAST_DAYS))
+                .borders(Borders::ALL),
+        )
+        .data(BarGroup::default().bars(&forecast_bars))
+        .bar_width(2)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Yellow));
+    frame.render_widget(forecast, rows[2]);
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(30),
+            Constraint::Percentage(30),
+            Constraint::Percentage(40),
+        ])
+        .split(rows[3]);
+    let histogram = |title: &'static str, data: &[(&'static str, u64)], color: Color| {
+        let bars: Vec<Bar> = data
+            .iter()
+            .map(|(label, value)| Bar::default().value(*value).label(Line::from(*label)))
+            .collect();
+        BarChart::default()
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(4)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(color))
+    };
+    frame.render_widget(
+        histogram("Interwały", &stats.interval_histogram, Color::Cyan),
+        bottom[0],
+    );
+    frame.render_widget(
+        histogram(
+            "Współczynnik łatwości",
+            &stats.ease_distribution,
+            Color::Magenta,
+        ),
+        bottom[1],
+    );
+
+    let start = today
+        - Duration::days(today.weekday().num_days_from_monday() as i64)
+        - Duration::weeks(HEATMAP_WEEKS - 1);
+    let max = stats.activity.values().copied().max().unwrap_or(0).max(1);
+    let heatmap: Vec<Line> = ["Pn", "Wt", "Śr", "Cz", "Pt", "So", "Nd"]
+        .iter()
+        .enumerate()
+        .map(|(weekday, label)| {
+            let mut spans = vec![Span::raw(format!("{} ", label))];
+            for week in 0..HEATMAP_WEEKS {
+                let date = start + Duration::days(week * 7 + weekday as i64);
+                if date > today {
+                    spans.push(Span::raw("  "));
+                    continue;
+                }
+                let count = stats.activity.get(&date).copied().unwrap_or(0);
+                let color = match count * 4 / max {
+                    _ if count == 0 => SLATE.c700,
+                    0 => Color::Rgb(14, 68, 41),
+                    1 => Color::Rgb(0, 109, 50),
+                    2 => Color::Rgb(38, 166, 65),
+                    _ => Color::Rgb(57, 211, 83),
+                };
+                spans.push(Span::styled("■ ", Style::default().fg(color)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let heatmap = Paragraph::new(heatmap).block(
+        Block::default()
+            .title(format!("Aktywność (ostatnie {} tygodni)", HEATMAP_WEEKS))
+            .borders(Borders::ALL),
+    );
+    frame.render_widget(heatmap, bottom[2]);
+}
+
+fn draw_deck_prompt(frame: &mut Frame, app: &App) {
+    let Some(prompt) = &app.deck_prompt else {
+        return;
+    };
+    let title = match &prompt.kind {
+        DeckPromptKind::Rename(_) => "Nowa nazwa talii".to_string(),
+        DeckPromptKind::Export { deck_index, format } => format!(
+            "Eksport {} do pliku — format: {} (Tab: zmień)",
+            match deck_index.and_then(|idx| app.collection.decks.get(idx)) {
+                Some(deck) => format!("talii \"{}\"", deck.get_name()),
+                None => "całej kolekcji".to_string(),
+            },
+            format.label()
+        ),
+        DeckPromptKind::UpdateFromSource(_) => {
+            "Ścieżka pliku, z którego zaktualizować talię".to_string()
+        }
+        DeckPromptKind::Split { .. } => "Nazwa nowej talii z zaznaczonych kart".to_string(),
+        DeckPromptKind::Limits(_) => {
+            "Dzienne limity talii: nowe karty/powtórki (np. 20/200)".to_string()
+        }
+        DeckPromptKind::LearningSteps(_) => {
+            "Kroki nauki / ponownej nauki (np. 1m 10m 1h / 10m)".to_string()
+        }
+        DeckPromptKind::TagFilter => {
+            "Filtr tagów, np. gramatyka and not czasowniki (puste: wyłącz)".to_string()
+        }
+    };
+    let area = centered_rect(50, 20, frame.area());
+    let popup_block = Block::default()
+        .title(title)
+        .border_type(BorderType::Rounded)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::DarkGray));
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup_block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(area);
+    let input = Paragraph::new(prompt.input.clone()).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::LightBlue).fg(Color::Black)),
+    );
+    frame.render_widget(input, chunks[0]);
+    if let Some(error) = &prompt.error {
+        frame.render_widget(
+            Paragraph::new(error.clone())
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: true }),
+            chunks[1],
+        );
+    }
+}
+
+fn draw_deck_deletion_confirmation(frame: &mut Frame, app: &App) {
+    let Some(deck) = app
+        .selected_deck_index()
+        .and_then(|idx| app.collection.decks.get(idx))
+    else {
+        return;
+    };
+    let area = centered_rect(50, 20, frame.area());
+    let popup = Paragraph::new(format!(
+        "Usunąć talię \"{}\" razem z {} kartami? (t/n)",
+        deck.get_name(),
+        deck.get_card_count()
+    ))
+    .centered()
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .title("Potwierdzenie")
+            .border_type(BorderType::Rounded)
+            .borders(Borders::ALL)
+            .padding(Padding::new(0, 0, area.height / 3, 0))
+            .style(Style::default().bg(Color::DarkGray)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
 }
+
+fn draw_source_change(frame: &mut Frame, app: &App) {
+    let Some(deck) = app
+        .source_change
+        .and_then(|idx| app.collection.decks.get(idx))
+    else {
+        return;
+    };
+    let path = deck
+        .get_source()
+        .map(|source| source.path.display().to_string())
+        .unwrap_or_default();
+    let area = centered_rect(50, 20, frame.area());
+    let popup = Paragraph::new(format!(
+        "Plik źródłowy talii \"{}\" zmienił się na dysku:\n{}\n\nWczytać zmiany z zachowaniem postępów? (t/n)",
+        deck.get_name(),
+        path
+    ))
+    .centered()
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .title("Zmiana źródła")
+            .border_type(BorderType::Rounded)
+            .borders(Borders::ALL)
+            .padding(Padding::new(0, 0, area.height / 4, 0))
+            .style(Style::default().bg(Color::DarkGray)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+fn draw_update_preview(frame: &mut Frame, app: &App) {
+    let Some(preview) = &app.update_preview else {
+        return;
+    };
+    let Some(deck) = app.collection.decks.get(preview.deck_index) else {
+        return;
+    };
+    let update = &preview.update;
+    let mut lines = vec![
+        Line::from(format!("Źródło: {}", preview.source)),
+        Line::from(format!(
+            "Nowe: {} | Zmienione: {} | Bez zmian: {} | Brak w źródle: {}",
+            update.added.len(),
+            update.changed.len(),
+            update.unchanged,
+            update.removed.len()
+        )),
+        Line::from(if preview.retire_removed {
+            "Karty, których nie ma w źródle, zostaną usunięte."
+        } else {
+            "Karty, których nie ma w źródle, zostaną w talii."
+        }),
+        Line::from(""),
+    ];
+    if update.is_empty() {
+        lines.push(Line::from("Talia jest zgodna ze źródłem."));
+    }
+    let single_line = |text: &str| text.lines().collect::<Vec<_>>().join(" / ");
+    for card in &update.added {
+        lines.push(Line::styled(
+            format!(
+                "+ {} → {}",
+                single_line(&card.get_question()),
+                single_line(&card.get_answer())
+            ),
+            Style::default().fg(Color::Green),
+        ));
+    }
+    for change in &update.changed {
+        let Some(card) = deck.get_card(change.index) else {
+            continue;
+        };
+        lines.push(Line::styled(
+            format!("~ {}", single_line(&change.question)),
+            Style::default().fg(Color::Yellow),
+        ));
+        if card.get_question().trim() != change.question.trim() {
+            lines.push(Line::from(format!(
+                "    pytanie: {} → {}",
+                single_line(&card.get_question()),
+                single_line(&change.question)
+            )));
+        }
+        if card.get_answer().trim() != change.answer.trim() {
+            lines.push(Line::from(format!(
+                "    odpowiedź: {} → {}",
+                single_line(&card.get_answer()),
+                single_line(&change.answer)
+            )));
+        }
+        if card.get_tags() != change.tags.as_slice() {
+            lines.push(Line::from(format!(
+                "    tagi: {} → {}",
+                card.get_tags().join(" "),
+                change.tags.join(" ")
+            )));
+        }
+    }
+    let removed_color = if preview.retire_removed {
+        Color::Red
+    } else {
+        Color::Gray
+    };
+    for &index in &update.removed {
+        if let Some(card) = deck.get_card(index) {
+            lines.push(Line::styled(
+                format!("- {}", single_line(&card.get_question())),
+                Style::default().fg(removed_color),
+            ));
+        }
+    }
+
+    let area = centered_rect(80, 80, frame.area());
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((preview.scroll, 0))
+        .block(
+            Block::default()
+                .title(format!("Aktualizacja talii \"{}\"", deck.get_name()))
+                .border_type(BorderType::Rounded)
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        );
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+fn draw_notification(frame: &mut Frame, app: &App) {
+    let Some(notification) = app.notifications.first() else {
+        return;
+    };
+    let color = match notification.kind {
+        NotificationKind::Info => Color::Green,
+        NotificationKind::Error => Color::Red,
+    };
+    let mut lines: Vec<Line> = notification
+        .lines
+        .iter()
+        .map(|line| Line::from(line.clone()))
+        .collect();
+    lines.push(Line::from(""));
+    let remaining = app.notifications.len() - 1;
+    let footer = if remaining > 0 {
+        format!("Enter/Esc: Zamknij (kolejne komunikaty: {})", remaining)
+    } else {
+        "Enter/Esc: Zamknij".to_string()
+    };
+    lines.push(Line::from(footer.italic()).centered());
+
+    let area = centered_rect(70, 50, frame.area());
+    let popup = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .title(Line::from(notification.title.clone().bold()).centered())
+            .border_type(BorderType::Rounded)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color))
+            .padding(Padding::horizontal(1))
+            .style(Style::default().bg(Color::DarkGray)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)